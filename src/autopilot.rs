@@ -0,0 +1,274 @@
+use super::player::{self, REST_COST};
+use super::{CombatStats, GameLog, Hidden, Item, Map, Monster, Position, Viewshed};
+use rltk::{Algorithm2D, BaseMap, Point};
+use specs::prelude::*;
+
+/// Resting heals one hp every this many turns
+const REST_TURNS_PER_HP: i32 = 5;
+
+/// Something the player keeps doing turn after turn, until something interesting happens
+#[derive(PartialEq, Clone, Debug)]
+pub enum AutoAction {
+    Rest,
+    Run {
+        dx: i32,
+        dy: i32,
+        sides: (bool, bool), // which tiles at the sides were open when it started
+    },
+    Travel {
+        path: Vec<usize>, // the tiles still to walk, the next one first
+    },
+}
+
+/// What the player is doing by themselves, if anything
+#[derive(Default)]
+pub struct AutoPilot {
+    pub action: Option<AutoAction>,
+    hp: i32, // at the last step, to notice getting hurt
+    rested: i32,
+}
+
+/// Starts resting, unless there is nothing to heal or danger is in sight
+pub fn rest(ecs: &mut World) {
+    if let Some((hp, max_hp)) = player_hp(ecs) {
+        if hp >= max_hp {
+            ecs.write_resource::<GameLog>()
+                .log("You are already at full health");
+            return;
+        }
+    }
+    start(ecs, AutoAction::Rest);
+}
+
+/// Starts running in a direction
+pub fn run(ecs: &mut World, dx: i32, dy: i32) {
+    let pos = *ecs.fetch::<Point>();
+    let sides = open_sides(&ecs.fetch::<Map>(), pos, dx, dy);
+    start(ecs, AutoAction::Run { dx, dy, sides });
+}
+
+/// The map as the player knows it: travelling goes only over the tiles already seen
+struct KnownMap<'a> {
+    map: &'a Map,
+}
+
+impl BaseMap for KnownMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+
+    /// Only the terrain counts, the monsters out of sight are not known: if one shows up
+    /// on the way, travelling stops
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let map = self.map;
+        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(tx, ty)| tx >= 1 && tx < map.width && ty >= 1 && ty < map.height)
+            .map(|(tx, ty)| map.xy_idx(tx, ty))
+            .filter(|&next| map.revealed_tiles[next] && !map.tiles[next].is_solid())
+            .map(|next| (next, 1.0))
+            .collect()
+    }
+}
+
+impl Algorithm2D for KnownMap<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+/// Starts walking to a tile the player has seen, along the shortest path
+pub fn travel(ecs: &mut World, target: Point) {
+    let path = {
+        let map = ecs.fetch::<Map>();
+        let pos = *ecs.fetch::<Point>();
+        let target_idx = map.xy_idx(target.x, target.y);
+        if !map.revealed_tiles[target_idx] || target == pos {
+            return;
+        }
+        let path = rltk::a_star_search(
+            map.xy_idx(pos.x, pos.y),
+            target_idx,
+            &KnownMap { map: &map },
+        );
+        if !path.success {
+            None
+        } else {
+            Some(path.steps.into_iter().skip(1).collect::<Vec<_>>())
+        }
+    };
+    match path {
+        Some(path) => start(ecs, AutoAction::Travel { path }),
+        None => ecs
+            .write_resource::<GameLog>()
+            .log("You can't find a way there"),
+    }
+}
+
+/// The tiles left to walk, if the player is travelling
+pub fn travel_path(ecs: &World) -> Vec<usize> {
+    match &ecs.fetch::<AutoPilot>().action {
+        Some(AutoAction::Travel { path }) => path.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn start(ecs: &mut World, action: AutoAction) {
+    if let Some(reason) = danger(ecs) {
+        ecs.write_resource::<GameLog>().log(reason);
+        return;
+    }
+    let hp = player_hp(ecs).map_or(0, |(hp, _)| hp);
+    *ecs.write_resource::<AutoPilot>() = AutoPilot {
+        action: Some(action),
+        hp,
+        rested: 0,
+    };
+}
+
+pub fn stop(ecs: &mut World) {
+    ecs.write_resource::<AutoPilot>().action = None;
+}
+
+pub fn is_active(ecs: &World) -> bool {
+    ecs.fetch::<AutoPilot>().action.is_some()
+}
+
+/// Takes the next step of what the player is doing by themselves. Returns the energy
+/// spent, or `None` if they stopped
+pub fn step(ecs: &mut World) -> Option<i32> {
+    let action = ecs.fetch::<AutoPilot>().action.clone()?;
+
+    // the same reasons stop resting, running and travelling
+    let hp = player_hp(ecs).map_or(0, |(hp, _)| hp);
+    let hurt = hp < ecs.fetch::<AutoPilot>().hp;
+    let reason = if hurt {
+        Some("You are hurt!")
+    } else {
+        danger(ecs)
+    };
+    if let Some(reason) = reason {
+        ecs.write_resource::<GameLog>().log(reason);
+        stop(ecs);
+        return None;
+    }
+
+    let cost = match action {
+        AutoAction::Rest => rest_step(ecs),
+        AutoAction::Run { dx, dy, sides } => run_step(ecs, dx, dy, sides),
+        AutoAction::Travel { path } => travel_step(ecs, path),
+    };
+    if cost.is_none() {
+        stop(ecs);
+    }
+    ecs.write_resource::<AutoPilot>().hp = player_hp(ecs).map_or(0, |(hp, _)| hp);
+    cost
+}
+
+fn rest_step(ecs: &mut World) -> Option<i32> {
+    let player = *ecs.fetch::<Entity>();
+    let mut stats = ecs.write_storage::<CombatStats>();
+    let stats = stats.get_mut(player)?;
+    if stats.hp >= stats.max_hp {
+        ecs.write_resource::<GameLog>().log("You feel rested");
+        return None;
+    }
+
+    let mut autopilot = ecs.write_resource::<AutoPilot>();
+    autopilot.rested += 1;
+    if autopilot.rested % REST_TURNS_PER_HP == 0 {
+        stats.hp += 1;
+    }
+    Some(REST_COST)
+}
+
+fn run_step(ecs: &mut World, dx: i32, dy: i32, sides: (bool, bool)) -> Option<i32> {
+    let start = *ecs.fetch::<Point>();
+    let cost = player::try_move_player(dx, dy, ecs);
+    let pos = *ecs.fetch::<Point>();
+    if cost == 0 || pos == start {
+        return None;
+    }
+
+    // stop on something to pick up, or where the walls around change: a junction, a door
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let on_item = (&items, &positions)
+        .join()
+        .any(|(_item, p)| p.x == pos.x && p.y == pos.y);
+    let changed = open_sides(&ecs.fetch::<Map>(), pos, dx, dy) != sides;
+    if on_item || changed {
+        ecs.write_resource::<AutoPilot>().action = None;
+    }
+    Some(cost)
+}
+
+fn travel_step(ecs: &mut World, mut path: Vec<usize>) -> Option<i32> {
+    let next = *path.first()?;
+    let (dx, dy) = {
+        let map = ecs.fetch::<Map>();
+        if map.blocked[next] {
+            drop(map);
+            ecs.write_resource::<GameLog>()
+                .log("Something is in the way");
+            return None;
+        }
+        let pos = *ecs.fetch::<Point>();
+        (
+            next as i32 % map.width - pos.x,
+            next as i32 / map.width - pos.y,
+        )
+    };
+
+    let cost = player::try_move_player(dx, dy, ecs);
+    let pos = *ecs.fetch::<Point>();
+    if cost == 0 || ecs.fetch::<Map>().xy_idx(pos.x, pos.y) != next {
+        return None;
+    }
+
+    path.remove(0);
+    ecs.write_resource::<AutoPilot>().action = if path.is_empty() {
+        None
+    } else {
+        Some(AutoAction::Travel { path })
+    };
+    Some(cost)
+}
+
+/// Whether a monster is in sight, shared by everything the player does by themselves
+pub fn danger(ecs: &World) -> Option<&'static str> {
+    let player = *ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let viewshed = viewsheds.get(player)?;
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let in_sight = (&monsters, &positions, !&hidden)
+        .join()
+        .any(|(_m, pos, _h)| viewshed.visible_tiles.contains(&Point::new(pos.x, pos.y)));
+    in_sight.then_some("There is a monster in sight")
+}
+
+fn player_hp(ecs: &World) -> Option<(i32, i32)> {
+    let player = *ecs.fetch::<Entity>();
+    let stats = ecs.read_storage::<CombatStats>();
+    stats.get(player).map(|s| (s.hp, s.max_hp))
+}
+
+/// Whether the tiles on the left and on the right of the direction are walkable
+fn open_sides(map: &Map, pos: Point, dx: i32, dy: i32) -> (bool, bool) {
+    let open = |x: i32, y: i32| {
+        x >= 0
+            && x < map.width
+            && y >= 0
+            && y < map.height
+            && !map.tiles[map.xy_idx(x, y)].is_solid()
+    };
+    (open(pos.x - dy, pos.y + dx), open(pos.x + dy, pos.y - dx))
+}
@@ -1,43 +1,417 @@
+use crate::{Map, Overworld};
 use rltk::RGB;
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
-use specs_derive::Component;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
+// the ConvertSaveload derive looks for this name
+use std::convert::Infallible as NoError;
 
 #[derive(Clone, PartialEq, Copy)]
 pub enum RunState {
-    Paused,
-    Running,
+    Title,                       // the splash screen at startup
+    Paused,                      // waiting for the player to act
+    Running,                     // simulating the world until it's the player's turn again
+    Targeting { target: usize }, // picking what to shoot at
+    Looking { x: i32, y: i32 },  // moving the cursor around to examine the map
+    LevelUp,                     // the player is picking a perk
+    ShowInventory,
+    ShowEquipment,
+    ShowHelp,
+    GameOver,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
-    pub glyph: rltk::FontCharType, // would like to have a texture here at some point
+    pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
+    pub render_order: i32, // lower is drawn on top
+    #[serde(default)]
+    pub sprite: Option<rltk::FontCharType>, // drawn instead of the glyph in tiles mode
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
+
+/// Energy accumulated each game tick: whoever reaches `TURN_COST` gets to act
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Initiative {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
+/// Where the player saw the entity for the last time
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LastSeen {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Torches, glowing monsters and the player's lantern
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct LightSource {
+    pub range: i32,
+    pub colour: RGB,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum AwarenessState {
+    Unaware,
+    Suspicious, // heard something, or lost track of the player
+    Hunting,    // sees the player
+}
+
+impl AwarenessState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AwarenessState::Unaware => "unaware",
+            AwarenessState::Suspicious => "suspicious",
+            AwarenessState::Hunting => "hunting you",
+        }
+    }
+}
+
+/// What a monster knows about the player
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Awareness {
+    pub state: AwarenessState,
+    pub investigate: Option<rltk::Point>, // where it heard or last saw the player
+}
+
+/// Follows the scent of the player when it can't see them anymore
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Tracker {}
+
+/// The player is moving carefully and makes less noise
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Sneaking {}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct CombatStats {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub defense: i32,
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToMelee {
+    pub target: Entity,
+}
+
+/// Damage taken this tick, summed up by the `DamageSystem`
+#[derive(Component, Debug)]
+pub struct SufferDamage {
+    pub amount: Vec<i32>,
+    pub by_player: bool, // the player gets the experience for the kill
+}
+
+impl SufferDamage {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        by_player: bool,
+    ) {
+        if let Some(suffering) = store.get_mut(victim) {
+            suffering.amount.push(amount);
+            suffering.by_player |= by_player;
+        } else {
+            let dmg = SufferDamage {
+                amount: vec![amount],
+                by_player,
+            };
+            store.insert(victim, dmg).expect("Unable to insert damage");
+        }
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32,
+    pub explored: i32, // tiles revealed that didn't give experience yet
+}
+
+/// Shoots at a distance: bows when equipped, and the monsters that spit or cast
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ranged {
+    pub range: i32,
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
+/// Only for show: moves along the path, and disappears at its end
+#[derive(Component, Debug, Clone)]
+pub struct Projectile {
+    pub path: Vec<rltk::Point>,
+    pub step: usize,
+    pub timer: f32, // ms since the last step
+}
+
+/// Short-lived effects: gone when their time runs out
+#[derive(Component, Debug, Clone)]
+pub struct ParticleLifetime {
+    pub lifetime_ms: f32,
+}
+
+// == items ==
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Item {}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InBackpack {
+    pub owner: Entity,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToPickupItem {
+    pub collected_by: Entity,
+    pub item: Entity,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+    Head,
+    Body,
+    Ranged,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 5] = [
+        EquipmentSlot::Melee,
+        EquipmentSlot::Shield,
+        EquipmentSlot::Head,
+        EquipmentSlot::Body,
+        EquipmentSlot::Ranged,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Melee => "Weapon",
+            EquipmentSlot::Shield => "Shield",
+            EquipmentSlot::Head => "Head",
+            EquipmentSlot::Body => "Body",
+            EquipmentSlot::Ranged => "Ranged",
+        }
+    }
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+/// Equips the item, or puts it back in the backpack if it's already worn
+#[derive(Component, Debug, Clone)]
+pub struct WantsToEquip {
+    pub item: Entity,
+}
+
+/// Used from the inventory, and gone afterwards
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Consumable {}
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToUseItem {
+    pub item: Entity,
+}
+
+/// Eating it resets the hunger clock
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesFood {}
+
+// == hunger ==
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+/// Counts down the player turns until the next hunger state
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+// == swimming ==
+/// Can get into the water, of the overworld and of the dungeons, a slow and tiring swim
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Swimmer {}
+
+/// Runs out while swimming, when it's over the swimmer starts drowning
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Breath {
+    pub breath: i32,
+    pub max_breath: i32,
+}
+
+/// Carried around, it floats whoever has it over any water
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Boat {}
+
+// == status effects ==
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusKind {
+    Poison,       // loses `magnitude` hp each turn
+    Confusion,    // moves in a random direction
+    Paralysis,    // skips turns
+    Regeneration, // gains `magnitude` hp each turn
+}
+
+impl StatusKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Confusion => "Confused",
+            StatusKind::Paralysis => "Paralysed",
+            StatusKind::Regeneration => "Regenerating",
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: i32,
+    pub magnitude: i32,
+}
+
+/// The timed effects active on an entity, ticked on each of its turns
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Adds an effect to the target. The same effect twice refreshes the duration,
+    /// except for the poison that also stacks up its damage
+    pub fn apply(store: &mut WriteStorage<StatusEffects>, target: Entity, effect: StatusEffect) {
+        if store.get(target).is_none() {
+            store
+                .insert(target, StatusEffects::default())
+                .expect("Unable to insert status effects");
+        }
+        let active = store.get_mut(target).unwrap();
+
+        match active.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            None => active.effects.push(effect),
+            Some(existing) => {
+                existing.turns = i32::max(existing.turns, effect.turns);
+                existing.magnitude = match effect.kind {
+                    StatusKind::Poison => existing.magnitude + effect.magnitude,
+                    _ => i32::max(existing.magnitude, effect.magnitude),
+                };
+            }
+        }
+    }
+}
+
+/// Gives a status effect: monsters when they hit, items when used
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub effect: StatusEffect,
+    pub chance: i32, // percent
+}
+
+// == traps ==
+/// Not drawn until the player spots it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum TrapKind {
+    Spikes,   // hurts
+    Teleport, // sends to a random room
+    Alarm,    // wakes up the monsters around
+    Flood,    // turns the area around into water
+}
+
+impl TrapKind {
+    /// Triggered once and then gone
+    pub fn single_use(&self) -> bool {
+        matches!(self, TrapKind::Alarm | TrapKind::Flood)
+    }
+}
+
+/// Goes off when something steps on its tile
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {
+    pub trap: TrapKind,
+}
+
+/// The entity moved this tick, so it may step on a trap
+#[derive(Component, Debug, Clone)]
+pub struct EntityMoved {}
+
+// == saving ==
+/// Marks the entities that are written in the savegame
+pub struct SerializeMe;
+
+/// Marks the entities of a level being put aside, while they are written
+pub struct StashMe;
+
+/// Carries the map in the savegame, as it is a resource and not a component
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: Map,
+    #[serde(default)]
+    pub overworld: Overworld,
+}
@@ -0,0 +1,112 @@
+use super::RenderMode;
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+const CONFIG_FILE: &str = "./config.json";
+
+/// The fonts shipped in `resources`
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum FontChoice {
+    Terminal8x8,
+    Vga8x16,
+}
+
+impl FontChoice {
+    pub fn file(&self) -> &'static str {
+        match self {
+            FontChoice::Terminal8x8 => "terminal8x8.jpg",
+            FontChoice::Vga8x16 => "vga8x16.jpg",
+        }
+    }
+
+    /// Width and height of a tile, in pixels
+    pub fn tile_size(&self) -> (u32, u32) {
+        match self {
+            FontChoice::Terminal8x8 => (8, 8),
+            FontChoice::Vga8x16 => (8, 16),
+        }
+    }
+}
+
+/// How the game window looks, chosen at startup and kept between runs
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)] // the options missing from older files get the default
+pub struct Config {
+    pub font: FontChoice,
+    pub scanlines: bool,   // the CRT post-processing shader
+    pub screen_burn: bool, // glow around bright glyphs, only with the scanlines
+    pub background: bool,  // `console_with_bg` or `console_no_bg` pipeline
+    pub render_mode: RenderMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            font: FontChoice::Terminal8x8,
+            scanlines: false,
+            screen_burn: false,
+            background: true,
+            render_mode: RenderMode::Ascii,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, falling back to the defaults if it's missing or broken
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Config {
+        fs::read_to_string(CONFIG_FILE)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Config {
+        Config::default()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        let data = serde_json::to_string_pretty(self).expect("Unable to serialize config");
+        if let Err(e) = fs::write(CONFIG_FILE, data) {
+            rltk::console::log(format!("Unable to write {}: {}", CONFIG_FILE, e));
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {}
+
+    /// Applies the command line options, like `--font vga --scanlines`.
+    /// Returns `true` if anything changed, so it can be saved
+    pub fn apply_args(&mut self, mut args: impl Iterator<Item = String>) -> bool {
+        let mut changed = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--font" => match args.next().as_deref() {
+                    Some("vga") => self.font = FontChoice::Vga8x16,
+                    Some("terminal") => self.font = FontChoice::Terminal8x8,
+                    _ => {
+                        rltk::console::log("--font wants `vga` or `terminal`");
+                        continue;
+                    }
+                },
+                "--scanlines" => self.scanlines = true,
+                "--no-scanlines" => self.scanlines = false,
+                "--screen-burn" => self.screen_burn = true,
+                "--no-screen-burn" => self.screen_burn = false,
+                "--bg" => self.background = true,
+                "--no-bg" => self.background = false,
+                "--tiles" => self.render_mode = RenderMode::Tiles,
+                "--ascii" => self.render_mode = RenderMode::Ascii,
+                _ => {
+                    rltk::console::log(format!("Unknown option {}", arg));
+                    continue;
+                }
+            }
+            changed = true;
+        }
+        changed
+    }
+}
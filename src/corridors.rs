@@ -0,0 +1,285 @@
+use super::{Map, Rect};
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator};
+
+/// Going through a room costs this much more than digging through the rock,
+/// so the A* corridors go around them when they can
+const ROOM_DIG_COST: f32 = 20.;
+/// The widest corridor a random style digs
+pub const MAX_CORRIDOR_WIDTH: i32 = 3;
+
+/// Which rooms get a corridor between them
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RoomLinking {
+    Sequential,                        // each room to the one made before it
+    NearestNeighbour,                  // each room to the closest of the ones made before it
+    SpanningTree { extra_loops: i32 }, // the shortest corridors joining everything, plus some loops
+}
+
+/// How a corridor goes from a room to the other
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TunnelShape {
+    LShaped,  // straight, turning once
+    AStar,    // the cheapest way, around the other rooms
+    Diagonal, // a straight line
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct CorridorStyle {
+    pub linking: RoomLinking,
+    pub shape: TunnelShape,
+    pub width: i32,
+}
+
+impl Default for CorridorStyle {
+    /// The corridors of the first versions of the game
+    fn default() -> Self {
+        CorridorStyle {
+            linking: RoomLinking::Sequential,
+            shape: TunnelShape::LShaped,
+            width: 2,
+        }
+    }
+}
+
+impl CorridorStyle {
+    pub fn random(rng: &mut RandomNumberGenerator) -> CorridorStyle {
+        let linking = match rng.roll_dice(1, 3) {
+            1 => RoomLinking::Sequential,
+            2 => RoomLinking::NearestNeighbour,
+            _ => RoomLinking::SpanningTree {
+                extra_loops: rng.range(1, 4),
+            },
+        };
+        let shape = match rng.roll_dice(1, 3) {
+            1 => TunnelShape::LShaped,
+            2 => TunnelShape::AStar,
+            _ => TunnelShape::Diagonal,
+        };
+        CorridorStyle {
+            linking,
+            shape,
+            width: rng.range(1, MAX_CORRIDOR_WIDTH + 1),
+        }
+    }
+}
+
+/// Joins the rooms of the map with corridors
+pub fn dig_corridors(map: &mut Map, style: CorridorStyle, rng: &mut RandomNumberGenerator) {
+    let rooms = map.rooms.clone();
+    for (a, b) in links(&rooms, style.linking, rng) {
+        let from = Point::from(rooms[a].center());
+        let to = Point::from(rooms[b].center());
+        let path = match style.shape {
+            TunnelShape::LShaped => l_path(from, to, rng.range(0, 2) == 1),
+            TunnelShape::Diagonal => rltk::line2d(rltk::LineAlg::Bresenham, from, to),
+            TunnelShape::AStar => a_star_path(map, &rooms, a, b),
+        };
+        map.dig_path(&path, style.width);
+    }
+}
+
+/// Two straight lines, turning at a corner
+pub fn l_path(from: Point, to: Point, horizontal_first: bool) -> Vec<Point> {
+    let corner = if horizontal_first {
+        Point::new(to.x, from.y)
+    } else {
+        Point::new(from.x, to.y)
+    };
+    let mut path = rltk::line2d(rltk::LineAlg::Bresenham, from, corner);
+    path.extend(rltk::line2d(rltk::LineAlg::Bresenham, corner, to));
+    path
+}
+
+/// The pairs of rooms to join, by their index
+fn links(
+    rooms: &[Rect],
+    linking: RoomLinking,
+    rng: &mut RandomNumberGenerator,
+) -> Vec<(usize, usize)> {
+    let distance = |a: usize, b: usize| {
+        rltk::DistanceAlg::Pythagoras.distance2d(
+            Point::from(rooms[a].center()),
+            Point::from(rooms[b].center()),
+        )
+    };
+    let mut links = Vec::new();
+
+    match linking {
+        RoomLinking::Sequential => {
+            for i in 1..rooms.len() {
+                links.push((i - 1, i));
+            }
+        }
+        RoomLinking::NearestNeighbour => {
+            for i in 1..rooms.len() {
+                let nearest = (0..i)
+                    .min_by(|a, b| distance(i, *a).total_cmp(&distance(i, *b)))
+                    .unwrap();
+                links.push((nearest, i));
+            }
+        }
+        RoomLinking::SpanningTree { extra_loops } => {
+            // Prim: the shortest link from the rooms already joined to one that isn't yet
+            let mut joined = vec![false; rooms.len()];
+            if let Some(first) = joined.first_mut() {
+                *first = true;
+            }
+            for _ in 1..rooms.len() {
+                let mut best: Option<(usize, usize)> = None;
+                for a in (0..rooms.len()).filter(|a| joined[*a]) {
+                    for b in (0..rooms.len()).filter(|b| !joined[*b]) {
+                        if best.is_none_or(|(x, y)| distance(a, b) < distance(x, y)) {
+                            best = Some((a, b));
+                        }
+                    }
+                }
+                let (a, b) = best.unwrap();
+                joined[b] = true;
+                links.push((a, b));
+            }
+
+            // and a few more, so there is more than one way around
+            for _ in 0..extra_loops {
+                if rooms.len() < 3 {
+                    break;
+                }
+                let a = rng.range(0, rooms.len());
+                let mut others: Vec<usize> = (0..rooms.len())
+                    .filter(|b| *b != a && !links.contains(&(a, *b)) && !links.contains(&(*b, a)))
+                    .collect();
+                others.sort_by(|x, y| distance(a, *x).total_cmp(&distance(a, *y)));
+                if let Some(b) = others.first() {
+                    links.push((a, *b));
+                }
+            }
+        }
+    }
+    links
+}
+
+/// The cheapest way from the centre of a room to the other, avoiding the rooms in between
+fn a_star_path(map: &Map, rooms: &[Rect], from: usize, to: usize) -> Vec<Point> {
+    let grid = DiggingGrid::new(map, rooms, from, to);
+    let start = Point::from(rooms[from].center());
+    let end = Point::from(rooms[to].center());
+    let path = rltk::a_star_search(
+        map.xy_idx(start.x, start.y),
+        map.xy_idx(end.x, end.y),
+        &grid,
+    );
+    if !path.success {
+        return l_path(start, end, true);
+    }
+    path.steps
+        .iter()
+        .map(|idx| Point::new(*idx as i32 % map.width, *idx as i32 / map.width))
+        .collect()
+}
+
+/// The map as seen by someone digging: everything can be dug, but rooms are expensive
+struct DiggingGrid {
+    width: i32,
+    height: i32,
+    cost: Vec<f32>,
+}
+
+impl DiggingGrid {
+    fn new(map: &Map, rooms: &[Rect], from: usize, to: usize) -> DiggingGrid {
+        let mut cost = vec![1.; (map.width * map.height) as usize];
+        for (i, room) in rooms.iter().enumerate() {
+            if i == from || i == to {
+                continue;
+            }
+            for tile in room.expand(1).interior() {
+                if tile.x >= 0 && tile.x < map.width && tile.y >= 0 && tile.y < map.height {
+                    cost[map.xy_idx(tile.x, tile.y)] = ROOM_DIG_COST;
+                }
+            }
+        }
+        DiggingGrid {
+            width: map.width,
+            height: map.height,
+            cost,
+        }
+    }
+}
+
+impl BaseMap for DiggingGrid {
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        let w = self.width as usize;
+        let p1 = Point::new(idx1 % w, idx1 / w);
+        let p2 = Point::new(idx2 % w, idx2 / w);
+        rltk::DistanceAlg::Manhattan.distance2d(p1, p2)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = rltk::SmallVec::new();
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
+        let w = self.width as usize;
+
+        // the border of the map stays solid
+        if x > 1 {
+            exits.push((idx - 1, self.cost[idx - 1]));
+        }
+        if x < self.width - 2 {
+            exits.push((idx + 1, self.cost[idx + 1]));
+        }
+        if y > 1 {
+            exits.push((idx - w, self.cost[idx - w]));
+        }
+        if y < self.height - 2 {
+            exits.push((idx + w, self.cost[idx + w]));
+        }
+        exits
+    }
+}
+
+impl Algorithm2D for DiggingGrid {
+    fn dimensions(&self) -> Point {
+        Point::new(self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every room reaches room 0 following the links
+    fn all_joined(rooms: usize, links: &[(usize, usize)]) -> bool {
+        let mut joined = vec![false; rooms];
+        joined[0] = true;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (a, b) in links {
+                if joined[*a] != joined[*b] {
+                    joined[*a] = true;
+                    joined[*b] = true;
+                    changed = true;
+                }
+            }
+        }
+        joined.iter().all(|j| *j)
+    }
+
+    #[test]
+    fn every_linking_joins_all_the_rooms() {
+        let mut rng = RandomNumberGenerator::seeded(42);
+        for linking in [
+            RoomLinking::Sequential,
+            RoomLinking::NearestNeighbour,
+            RoomLinking::SpanningTree { extra_loops: 0 },
+            RoomLinking::SpanningTree { extra_loops: 3 },
+        ] {
+            for count in 1..20 {
+                let rooms: Vec<Rect> = (0..count)
+                    .map(|_| Rect::new(rng.range(1, 70), rng.range(1, 40), 6, 6))
+                    .collect();
+                let links = links(&rooms, linking, &mut rng);
+                assert!(all_joined(rooms.len(), &links), "{:?}", linking);
+                assert!(links.iter().all(|(a, b)| a != b));
+            }
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use super::{
+    xp_for_kill, CombatStats, Experience, GameLog, Name, Player, Position, RunState, SufferDamage,
+};
+use crate::spawner;
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+/// Percent chance that a dead monster leaves a ration behind
+const RATION_DROP_CHANCE: i32 = 30;
+
+pub struct DamageSystem {}
+
+impl<'a> System<'a> for DamageSystem {
+    type SystemData = (
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Experience>,
+        ReadExpect<'a, Entity>, // the player, who gets the experience for the kills
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut stats, mut damage, mut experience, player) = data;
+
+        for (stats, damage) in (&mut stats, &damage).join() {
+            let was_alive = stats.hp > 0;
+            stats.hp -= damage.amount.iter().sum::<i32>();
+
+            if was_alive && stats.hp <= 0 && damage.by_player {
+                if let Some(exp) = experience.get_mut(*player) {
+                    exp.xp += xp_for_kill(stats);
+                }
+            }
+        }
+
+        damage.clear();
+    }
+}
+
+/// Removes from the world everything that died this tick
+pub fn delete_the_dead(ecs: &mut World) {
+    let mut dead: Vec<Entity> = Vec::new();
+    let mut drops: Vec<(i32, i32)> = Vec::new();
+    {
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let players = ecs.read_storage::<Player>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let entities = ecs.entities();
+        let mut log = ecs.write_resource::<GameLog>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        for (entity, stats) in (&entities, &combat_stats).join() {
+            if stats.hp > 0 {
+                continue;
+            }
+            if players.get(entity).is_some() {
+                let mut runstate = ecs.write_resource::<RunState>();
+                *runstate = RunState::GameOver;
+            } else {
+                if let Some(name) = names.get(entity) {
+                    log.log(format!("{} is dead", name.name));
+                }
+                dead.push(entity);
+
+                // some monsters carried something to eat
+                if let Some(pos) = positions.get(entity) {
+                    if rng.roll_dice(1, 100) <= RATION_DROP_CHANCE {
+                        drops.push((pos.x, pos.y));
+                    }
+                }
+            }
+        }
+    }
+
+    for victim in dead {
+        ecs.delete_entity(victim).expect("Unable to delete");
+    }
+    for (x, y) in drops {
+        spawner::ration(ecs, x, y);
+    }
+}
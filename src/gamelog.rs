@@ -0,0 +1,11 @@
+/// Messages shown to the player in the GUI panel, newest last
+#[derive(Default)]
+pub struct GameLog {
+    pub entries: Vec<String>,
+}
+
+impl GameLog {
+    pub fn log<S: ToString>(&mut self, message: S) {
+        self.entries.push(message.to_string());
+    }
+}
@@ -0,0 +1,529 @@
+use super::saveload_system;
+use super::{
+    xp_to_next_level, Action, Awareness, Breath, CombatStats, EquipmentSlot, Equipped, Experience,
+    GameLog, Hidden, HungerClock, HungerState, InBackpack, KeyMap, Map, Monster, Name, Perk,
+    Player, Position, Ranged, RexAssets, StatusEffects, Viewshed, MAPHEIGHT,
+};
+use rltk::{Point, Rltk, VirtualKeyCode, RGB};
+use specs::prelude::*;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ItemMenuResult {
+    Cancel,
+    NoResponse,
+    Selected,
+}
+
+/// Draws the panel below the map, with the player stats and the log
+pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
+    let top = MAPHEIGHT as i32;
+    ctx.draw_box(
+        0,
+        top,
+        79,
+        6,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+
+    let players = ecs.read_storage::<Player>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let experience = ecs.read_storage::<Experience>();
+    for (_player, stats, exp) in (&players, &combat_stats, &experience).join() {
+        let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
+        ctx.print_color(
+            2,
+            top,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            &health,
+        );
+        ctx.draw_bar_horizontal(
+            18,
+            top,
+            24,
+            stats.hp,
+            stats.max_hp,
+            RGB::named(rltk::RED),
+            RGB::named(rltk::BLACK),
+        );
+
+        let needed = xp_to_next_level(exp.level);
+        let level = format!(" Level {}  XP: {} / {} ", exp.level, exp.xp, needed);
+        ctx.print_color(
+            44,
+            top,
+            RGB::named(rltk::CYAN),
+            RGB::named(rltk::BLACK),
+            &level,
+        );
+        ctx.draw_bar_horizontal(
+            68,
+            top,
+            10,
+            exp.xp,
+            needed,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+        );
+    }
+
+    // how hungry the player is, unless it's nothing to worry about
+    let hunger = ecs.read_storage::<HungerClock>();
+    for (_player, clock) in (&players, &hunger).join() {
+        let (text, colour) = match clock.state {
+            HungerState::WellFed => ("Well Fed", rltk::GREEN),
+            HungerState::Normal => ("", rltk::WHITE),
+            HungerState::Hungry => ("Hungry", rltk::ORANGE),
+            HungerState::Starving => ("Starving", rltk::RED),
+        };
+        ctx.print_color(
+            71,
+            top + 5,
+            RGB::named(colour),
+            RGB::named(rltk::BLACK),
+            text,
+        );
+    }
+
+    // the breath left, only while it's running out, on the last row inside the box
+    let breaths = ecs.read_storage::<Breath>();
+    for (_player, breath) in (&players, &breaths).join() {
+        if breath.breath >= breath.max_breath {
+            continue;
+        }
+        let text = format!(" Breath: {} / {} ", breath.breath, breath.max_breath);
+        ctx.print_color(
+            44,
+            top + 5,
+            RGB::named(rltk::LIGHT_BLUE),
+            RGB::named(rltk::BLACK),
+            &text,
+        );
+        ctx.draw_bar_horizontal(
+            62,
+            top + 5,
+            8,
+            breath.breath,
+            breath.max_breath,
+            RGB::named(rltk::LIGHT_BLUE),
+            RGB::named(rltk::BLACK),
+        );
+    }
+
+    // the active status effects, on the right
+    let status = ecs.read_storage::<StatusEffects>();
+    for (_player, active) in (&players, &status).join() {
+        for (y, effect) in (top + 1..).zip(active.effects.iter().take(4)) {
+            ctx.print_color(
+                62,
+                y,
+                RGB::named(rltk::ORANGE),
+                RGB::named(rltk::BLACK),
+                format!("{} ({})", effect.kind.name(), effect.turns),
+            );
+        }
+    }
+
+    // the newest messages, as many as they fit in the box
+    let log = ecs.fetch::<GameLog>();
+    for (y, entry) in (top + 1..).zip(log.entries.iter().rev().take(5)) {
+        ctx.print(2, y, entry);
+    }
+}
+
+/// Shows the perks to choose from, returns the one picked if any
+pub fn level_up_menu(ctx: &mut Rltk) -> Option<Perk> {
+    let height = Perk::ALL.len() as i32 + 3;
+    let y = 20;
+    ctx.draw_box(
+        15,
+        y - 2,
+        50,
+        height,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Level up! Choose a perk",
+    );
+
+    for (i, perk) in Perk::ALL.iter().enumerate() {
+        let letter = (b'a' + i as u8) as char;
+        ctx.print(
+            17,
+            y + i as i32,
+            format!("({}) {}", letter, perk.description()),
+        );
+    }
+
+    match ctx.key {
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection >= 0 && (selection as usize) < Perk::ALL.len() {
+                Some(Perk::ALL[selection as usize])
+            } else {
+                None
+            }
+        }
+        None => None,
+    }
+}
+
+/// Draws a box with a lettered list of options, returns the index picked if any
+fn menu(ctx: &mut Rltk, title: &str, options: &[String]) -> (ItemMenuResult, Option<usize>) {
+    let count = options.len() as i32;
+    let y = 25 - (count / 2);
+    ctx.draw_box(
+        15,
+        y - 2,
+        40,
+        count + 3,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        title,
+    );
+    ctx.print_color(
+        18,
+        y + count + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to cancel",
+    );
+
+    for (i, option) in options.iter().enumerate() {
+        let letter = (b'a' + i as u8) as char;
+        ctx.print(17, y + i as i32, format!("({}) {}", letter, option));
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection >= 0 && selection < count {
+                (ItemMenuResult::Selected, Some(selection as usize))
+            } else {
+                (ItemMenuResult::NoResponse, None)
+            }
+        }
+    }
+}
+
+/// The items in the player's backpack, returns the one picked if any
+pub fn show_inventory(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let entities = ecs.entities();
+
+    let items: Vec<(Entity, String)> = (&entities, &backpack, &names)
+        .join()
+        .filter(|(_item, pack, _name)| pack.owner == *player)
+        .map(|(item, _pack, name)| (item, name.name.clone()))
+        .collect();
+    let options: Vec<String> = items.iter().map(|(_item, name)| name.clone()).collect();
+
+    match menu(ctx, "Inventory", &options) {
+        (ItemMenuResult::Selected, Some(i)) => (ItemMenuResult::Selected, Some(items[i].0)),
+        (result, _) => (result, None),
+    }
+}
+
+/// What the player is wearing, one line per slot. Returns the item picked if any
+pub fn show_equipment(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let entities = ecs.entities();
+
+    let worn: Vec<Option<Entity>> = EquipmentSlot::ALL
+        .iter()
+        .map(|slot| {
+            (&entities, &equipped)
+                .join()
+                .find(|(_item, eq)| eq.owner == *player && eq.slot == *slot)
+                .map(|(item, _eq)| item)
+        })
+        .collect();
+    let options: Vec<String> = EquipmentSlot::ALL
+        .iter()
+        .zip(worn.iter())
+        .map(|(slot, item)| {
+            let name = match item {
+                Some(item) => names.get(*item).unwrap().name.clone(),
+                None => "-".to_string(),
+            };
+            format!("{:<8}{}", slot.name(), name)
+        })
+        .collect();
+
+    match menu(ctx, "Equipment (pick to take off)", &options) {
+        (ItemMenuResult::Selected, Some(i)) => match worn[i] {
+            Some(item) => (ItemMenuResult::Selected, Some(item)),
+            None => (ItemMenuResult::NoResponse, None),
+        },
+        (result, _) => (result, None),
+    }
+}
+
+/// Picks where to shoot: Tab cycles through the monsters in range, Enter fires,
+/// or click on a tile. `selected` is the monster currently aimed at
+pub fn ranged_target(
+    ecs: &World,
+    ctx: &mut Rltk,
+    selected: &mut usize,
+) -> (ItemMenuResult, Option<Point>) {
+    let player = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let range = match player_ranged_weapon(ecs, *player) {
+        Some(ranged) => ranged.range,
+        None => return (ItemMenuResult::Cancel, None),
+    };
+    let in_range = |p: Point| {
+        rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, p) <= range as f32
+            && map.visible_tiles[map.xy_idx(p.x, p.y)]
+    };
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Select target: Tab next, Enter fire, Escape cancel",
+    );
+
+    // what can be reached
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    if let Some(viewshed) = viewsheds.get(*player) {
+        for tile in viewshed.visible_tiles.iter().filter(|t| in_range(**t)) {
+            ctx.set_bg(tile.x, tile.y, RGB::named(rltk::DARK_BLUE));
+        }
+    }
+
+    // the monsters in sight, the closest first
+    let positions = ecs.read_storage::<Position>();
+    let monsters = ecs.read_storage::<Monster>();
+    let mut targets: Vec<Point> = (&positions, &monsters)
+        .join()
+        .map(|(pos, _monster)| Point::new(pos.x, pos.y))
+        .filter(|p| in_range(*p))
+        .collect();
+    targets.sort_by(|a, b| {
+        let da = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *a);
+        let db = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *b);
+        da.total_cmp(&db)
+    });
+
+    // the mouse wins over the keyboard
+    let (mx, my) = ctx.mouse_pos();
+    let mouse = Point::new(mx, my);
+    if mx >= 0 && mx < map.width && my >= 0 && my < map.height && in_range(mouse) {
+        ctx.set_bg(mx, my, RGB::named(rltk::CYAN));
+        if ctx.left_click {
+            return (ItemMenuResult::Selected, Some(mouse));
+        }
+    }
+
+    if !targets.is_empty() {
+        *selected %= targets.len();
+        let target = targets[*selected];
+        ctx.set_bg(target.x, target.y, RGB::named(rltk::CYAN));
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(VirtualKeyCode::Tab) => {
+            *selected += 1;
+            (ItemMenuResult::NoResponse, None)
+        }
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::Z) if !targets.is_empty() => {
+            (ItemMenuResult::Selected, Some(targets[*selected]))
+        }
+        _ => (ItemMenuResult::NoResponse, None),
+    }
+}
+
+/// Moves a cursor over the map and describes what is under it. What is in sight is told in
+/// full, the rest of the map only as it is remembered
+pub fn look(ecs: &World, ctx: &mut Rltk, cursor: &mut Point) -> ItemMenuResult {
+    let map = ecs.fetch::<Map>();
+
+    // the cursor moves with the same keys as the player
+    let action = ctx
+        .key
+        .and_then(|key| ecs.fetch::<KeyMap>().action(key, ctx.shift));
+    let (dx, dy) = match (ctx.key, action) {
+        (Some(VirtualKeyCode::Escape), _) | (_, Some(Action::Look)) => {
+            return ItemMenuResult::Cancel
+        }
+        (_, Some(Action::MoveN)) => (0, -1),
+        (_, Some(Action::MoveS)) => (0, 1),
+        (_, Some(Action::MoveW)) => (-1, 0),
+        (_, Some(Action::MoveE)) => (1, 0),
+        _ => (0, 0),
+    };
+    cursor.x = (cursor.x + dx).clamp(0, map.width - 1);
+    cursor.y = (cursor.y + dy).clamp(0, map.height - 1);
+    ctx.set_bg(cursor.x, cursor.y, RGB::named(rltk::MAGENTA));
+
+    let idx = map.xy_idx(cursor.x, cursor.y);
+    let mut lines = Vec::new();
+    if map.visible_tiles[idx] {
+        lines.push(format!("You see {}", map.tiles[idx].name()));
+
+        let names = ecs.read_storage::<Name>();
+        let hidden = ecs.read_storage::<Hidden>();
+        let stats = ecs.read_storage::<CombatStats>();
+        let awareness = ecs.read_storage::<Awareness>();
+        let positions = ecs.read_storage::<Position>();
+        let entities = ecs.entities();
+        for (ent, pos, name, _hidden) in (&entities, &positions, &names, !&hidden).join() {
+            if pos.x != cursor.x || pos.y != cursor.y {
+                continue;
+            }
+            let mut line = name.name.clone();
+            if let Some(stats) = stats.get(ent) {
+                line += &format!(" ({} / {} hp)", stats.hp, stats.max_hp);
+            }
+            if let Some(aware) = awareness.get(ent) {
+                line += &format!(", {}", aware.state.name());
+            }
+            lines.push(line);
+        }
+    } else if map.revealed_tiles[idx] {
+        lines.push(format!("You remember {}", map.tiles[idx].name()));
+    } else {
+        lines.push("You don't know what is there".to_string());
+    }
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Look around: move the cursor, Escape to stop",
+    );
+    for (y, line) in (1..).zip(lines.iter()) {
+        ctx.print_color(5, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), line);
+    }
+
+    ItemMenuResult::NoResponse
+}
+
+/// Lists the actions with the keys bound to them, returns `true` when it's closed
+pub fn help_screen(ecs: &World, ctx: &mut Rltk) -> bool {
+    let keymap = ecs.fetch::<KeyMap>();
+    let height = Action::ALL.len() as i32 + 3;
+    let y = (50 - height) / 2;
+    ctx.draw_box(
+        10,
+        y,
+        60,
+        height,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        13,
+        y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        " Keys ",
+    );
+    for (row, action) in (y + 2..).zip(Action::ALL.iter()) {
+        let keys = keymap
+            .keys(*action)
+            .iter()
+            .map(|k| k.label())
+            .collect::<Vec<_>>()
+            .join(", ");
+        ctx.print(12, row, action.description());
+        ctx.print_color(
+            46,
+            row,
+            RGB::named(rltk::CYAN),
+            RGB::named(rltk::BLACK),
+            if keys.is_empty() { "-" } else { &keys },
+        );
+    }
+    ctx.print_color(
+        13,
+        y + height,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        " Any key to close, keys.json to change them ",
+    );
+
+    ctx.key.is_some()
+}
+
+/// The ranged weapon the player is wearing, if any
+pub fn player_ranged_weapon(ecs: &World, player: Entity) -> Option<Ranged> {
+    let ranged = ecs.read_storage::<Ranged>();
+    let equipped = ecs.read_storage::<Equipped>();
+    (&ranged, &equipped)
+        .join()
+        .find(|(_r, eq)| eq.owner == player)
+        .map(|(r, _eq)| r.clone())
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum TitleSelection {
+    NewGame,
+    LoadGame,
+    Quit,
+}
+
+/// The splash screen, drawn from the REX Paint image
+pub fn title_screen(ecs: &World, ctx: &mut Rltk) -> Option<TitleSelection> {
+    let rex = ecs.fetch::<RexAssets>();
+    let layer = &rex.title.layers[0];
+    let (width, height) = (layer.width as i32, layer.height as i32);
+    ctx.render_xp_sprite(&rex.title, (80 - width) / 2, 3);
+
+    let text_y = height + 6;
+    ctx.print_color_centered(
+        text_y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Kerogueli",
+    );
+    ctx.print_centered(text_y + 2, "Enter: new game");
+    let can_load = saveload_system::does_save_exist();
+    if can_load {
+        ctx.print_centered(text_y + 3, "L: load game");
+    }
+    ctx.print_centered(text_y + 4, "Escape: quit");
+
+    match ctx.key {
+        Some(VirtualKeyCode::Return) => Some(TitleSelection::NewGame),
+        Some(VirtualKeyCode::L) if can_load => Some(TitleSelection::LoadGame),
+        Some(VirtualKeyCode::Escape) => Some(TitleSelection::Quit),
+        _ => None,
+    }
+}
+
+/// Returns `true` when the player wants to leave the game
+pub fn game_over(ctx: &mut Rltk) -> bool {
+    ctx.print_color_centered(
+        20,
+        RGB::named(rltk::RED),
+        RGB::named(rltk::BLACK),
+        "You are dead",
+    );
+    ctx.print_centered(22, "Press Escape to quit");
+
+    matches!(ctx.key, Some(VirtualKeyCode::Escape))
+}
@@ -0,0 +1,55 @@
+use super::{Awareness, AwarenessState, Monster, Position};
+use rltk::Point;
+use specs::prelude::*;
+
+/// A sound made somewhere in the map, heard by monsters within `radius`
+pub struct Noise {
+    pub x: i32,
+    pub y: i32,
+    pub radius: f32,
+}
+
+/// The noises made since the last tick
+#[derive(Default)]
+pub struct NoiseEvents {
+    pub events: Vec<Noise>,
+}
+
+impl NoiseEvents {
+    pub fn make(&mut self, x: i32, y: i32, radius: f32) {
+        if radius > 0. {
+            self.events.push(Noise { x, y, radius });
+        }
+    }
+}
+
+pub struct HearingSystem {}
+
+impl<'a> System<'a> for HearingSystem {
+    type SystemData = (
+        WriteExpect<'a, NoiseEvents>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, Awareness>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut noises, positions, monsters, mut awareness) = data;
+
+        for noise in noises.events.iter() {
+            let origin = Point::new(noise.x, noise.y);
+
+            // sound goes through walls, so only the distance matters
+            for (pos, _monster, aware) in (&positions, &monsters, &mut awareness).join() {
+                let distance =
+                    rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), origin);
+                if distance > noise.radius || aware.state == AwarenessState::Hunting {
+                    continue;
+                }
+                aware.state = AwarenessState::Suspicious;
+                aware.investigate = Some(origin);
+            }
+        }
+        noises.events.clear();
+    }
+}
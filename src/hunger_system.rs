@@ -0,0 +1,65 @@
+use super::{GameLog, HungerClock, HungerState, MyTurn, SufferDamage};
+use specs::prelude::*;
+
+// how many turns each state lasts before the next one
+pub const WELL_FED_TURNS: i32 = 150;
+pub const NORMAL_TURNS: i32 = 300;
+pub const HUNGRY_TURNS: i32 = 150;
+
+/// Damage taken every turn while starving
+const STARVATION_DAMAGE: i32 = 1;
+
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, HungerClock>,
+        ReadStorage<'a, MyTurn>,
+        ReadExpect<'a, Entity>, // the player
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    // the clock ticks once per turn of whoever has it
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut hunger_clock, turns, player, mut damage, mut log) = data;
+
+        for (ent, clock, _turn) in (&entities, &mut hunger_clock, &turns).join() {
+            clock.duration -= 1;
+            if clock.duration > 0 {
+                continue;
+            }
+
+            match clock.state {
+                HungerState::WellFed => {
+                    clock.state = HungerState::Normal;
+                    clock.duration = NORMAL_TURNS;
+                    if ent == *player {
+                        log.log("You are no longer well fed");
+                    }
+                }
+                HungerState::Normal => {
+                    clock.state = HungerState::Hungry;
+                    clock.duration = HUNGRY_TURNS;
+                    if ent == *player {
+                        log.log("You are hungry");
+                    }
+                }
+                HungerState::Hungry => {
+                    clock.state = HungerState::Starving;
+                    clock.duration = 0;
+                    if ent == *player {
+                        log.log("You are starving!");
+                    }
+                }
+                HungerState::Starving => {
+                    if ent == *player {
+                        log.log("Your hunger pangs are getting painful!");
+                    }
+                    SufferDamage::new_damage(&mut damage, ent, STARVATION_DAMAGE, false);
+                }
+            }
+        }
+    }
+}
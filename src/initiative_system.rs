@@ -0,0 +1,41 @@
+use super::{Initiative, MyTurn, Player, RunState};
+use specs::prelude::*;
+
+/// Energy an entity needs to take an action. With a speed of 10 it acts every 10 ticks
+pub const TURN_COST: i32 = 100;
+
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, Player>,
+        WriteExpect<'a, RunState>,
+    );
+
+    // one call is one game tick
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut initiatives, mut turns, player, mut runstate) = data;
+
+        if *runstate != RunState::Running {
+            return;
+        }
+        turns.clear();
+
+        for (ent, initiative) in (&entities, &mut initiatives).join() {
+            initiative.energy += initiative.speed;
+            if initiative.energy < TURN_COST {
+                continue;
+            }
+
+            turns.insert(ent, MyTurn {}).expect("Unable to insert turn");
+
+            // the world stops until the player decides what to do
+            if player.get(ent).is_some() {
+                *runstate = RunState::Paused;
+            }
+        }
+    }
+}
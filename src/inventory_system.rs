@@ -0,0 +1,218 @@
+use super::{
+    Equippable, Equipped, GameLog, HungerClock, HungerState, InBackpack, InflictsStatus, Name,
+    ParticleBuilder, Position, ProvidesFood, StatusEffects, WantsToEquip, WantsToPickupItem,
+    WantsToUseItem, WELL_FED_TURNS,
+};
+use rltk::RGB;
+use specs::prelude::*;
+
+pub struct ItemCollectionSystem {}
+
+impl<'a> System<'a> for ItemCollectionSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>, // the player
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToPickupItem>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player, mut log, mut wants_pickup, mut positions, names, mut backpack) = data;
+
+        for pickup in wants_pickup.join() {
+            // off the map, into the backpack
+            positions.remove(pickup.item);
+            backpack
+                .insert(
+                    pickup.item,
+                    InBackpack {
+                        owner: pickup.collected_by,
+                    },
+                )
+                .expect("Unable to insert backpack entry");
+
+            if pickup.collected_by == *player {
+                log.log(format!(
+                    "You pick up the {}",
+                    names.get(pickup.item).unwrap().name
+                ));
+            }
+        }
+
+        wants_pickup.clear();
+    }
+}
+
+pub struct ItemEquipSystem {}
+
+impl<'a> System<'a> for ItemEquipSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>, // the player
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToEquip>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, InBackpack>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player,
+            mut log,
+            entities,
+            mut wants_equip,
+            names,
+            equippable,
+            mut equipped,
+            mut backpack,
+        ) = data;
+
+        for (wearer, wants) in (&entities, &wants_equip).join() {
+            let item_name = &names.get(wants.item).unwrap().name;
+
+            // already worn: take it off
+            if equipped.remove(wants.item).is_some() {
+                backpack
+                    .insert(wants.item, InBackpack { owner: wearer })
+                    .expect("Unable to insert backpack entry");
+                if wearer == *player {
+                    log.log(format!("You take off the {}", item_name));
+                }
+                continue;
+            }
+
+            let slot = match equippable.get(wants.item) {
+                Some(equippable) => equippable.slot,
+                None => {
+                    if wearer == *player {
+                        log.log(format!("You can't wear the {}", item_name));
+                    }
+                    continue;
+                }
+            };
+
+            // whatever is in the slot goes back in the backpack
+            let mut to_unequip: Vec<Entity> = Vec::new();
+            for (item, already_equipped) in (&entities, &equipped).join() {
+                if already_equipped.owner == wearer && already_equipped.slot == slot {
+                    to_unequip.push(item);
+                }
+            }
+            for item in to_unequip.iter() {
+                equipped.remove(*item);
+                backpack
+                    .insert(*item, InBackpack { owner: wearer })
+                    .expect("Unable to insert backpack entry");
+                if wearer == *player {
+                    log.log(format!(
+                        "You take off the {}",
+                        names.get(*item).unwrap().name
+                    ));
+                }
+            }
+
+            equipped
+                .insert(
+                    wants.item,
+                    Equipped {
+                        owner: wearer,
+                        slot,
+                    },
+                )
+                .expect("Unable to equip");
+            backpack.remove(wants.item);
+            if wearer == *player {
+                log.log(format!("You equip the {}", item_name));
+            }
+        }
+
+        wants_equip.clear();
+    }
+}
+
+const USE_PARTICLE_MS: f32 = 300.;
+
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>, // the player
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, HungerClock>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player,
+            mut log,
+            entities,
+            mut wants_use,
+            names,
+            inflicts_status,
+            mut status,
+            food,
+            mut hunger_clock,
+            positions,
+            mut particles,
+        ) = data;
+
+        for (user, wants) in (&entities, &wants_use).join() {
+            if user == *player {
+                log.log(format!(
+                    "You use the {}",
+                    names.get(wants.item).unwrap().name
+                ));
+            }
+
+            if let Some(inflicts) = inflicts_status.get(wants.item) {
+                StatusEffects::apply(&mut status, user, inflicts.effect);
+                if user == *player {
+                    log.log(format!(
+                        "You are {}",
+                        inflicts.effect.kind.name().to_lowercase()
+                    ));
+                }
+            }
+
+            if food.get(wants.item).is_some() {
+                if let Some(clock) = hunger_clock.get_mut(user) {
+                    clock.state = HungerState::WellFed;
+                    clock.duration = WELL_FED_TURNS;
+                    if user == *player {
+                        log.log("You are no longer hungry");
+                    }
+                }
+            }
+
+            if let Some(pos) = positions.get(user) {
+                particles.request(
+                    pos.x,
+                    pos.y,
+                    RGB::named(rltk::MAGENTA),
+                    rltk::to_cp437('*'),
+                    USE_PARTICLE_MS,
+                );
+            }
+
+            // consumables are gone once used
+            entities.delete(wants.item).expect("Delete failed");
+        }
+
+        wants_use.clear();
+    }
+}
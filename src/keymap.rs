@@ -0,0 +1,471 @@
+use rltk::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::ErrorKind;
+
+const KEYS_FILE: &str = "./keys.json";
+
+/// Everything the player can ask for with a key
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveN,
+    MoveS,
+    MoveW,
+    MoveE,
+    RunN,
+    RunS,
+    RunW,
+    RunE,
+    Wait,
+    Rest,
+    Pickup,
+    Inventory,
+    Equipment,
+    Aim,
+    Look,
+    Descend,
+    Ascend,
+    Teleport,
+    Sneak,
+    FogStyle,
+    RenderMode,
+    Save,
+    Load,
+    Help,
+    ScentOverlay,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::MoveN,
+        Action::MoveS,
+        Action::MoveW,
+        Action::MoveE,
+        Action::RunN,
+        Action::RunS,
+        Action::RunW,
+        Action::RunE,
+        Action::Wait,
+        Action::Rest,
+        Action::Pickup,
+        Action::Inventory,
+        Action::Equipment,
+        Action::Aim,
+        Action::Look,
+        Action::Descend,
+        Action::Ascend,
+        Action::Teleport,
+        Action::Sneak,
+        Action::FogStyle,
+        Action::RenderMode,
+        Action::Save,
+        Action::Load,
+        Action::Help,
+        Action::ScentOverlay,
+    ];
+
+    /// What the action does, for the help screen
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::MoveN => "Move up",
+            Action::MoveS => "Move down",
+            Action::MoveW => "Move left",
+            Action::MoveE => "Move right",
+            Action::RunN => "Run up",
+            Action::RunS => "Run down",
+            Action::RunW => "Run left",
+            Action::RunE => "Run right",
+            Action::Wait => "Wait a turn",
+            Action::Rest => "Rest until healed",
+            Action::Pickup => "Pick up an item",
+            Action::Inventory => "Inventory",
+            Action::Equipment => "Equipment",
+            Action::Aim => "Aim the ranged weapon",
+            Action::Look => "Look around",
+            Action::Descend => "Go down the stairs",
+            Action::Ascend => "Go up the stairs",
+            Action::Teleport => "Teleport to a random room",
+            Action::Sneak => "Sneak, or stop sneaking",
+            Action::FogStyle => "Change the fog of war",
+            Action::RenderMode => "Switch between glyphs and sprites",
+            Action::Save => "Save the game",
+            Action::Load => "Load the game",
+            Action::Help => "This help",
+            Action::ScentOverlay => "Show the scent trail (debug)",
+        }
+    }
+
+    /// The keys bound to the action when the keys file doesn't say otherwise
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::MoveN => &["Up", "K", "Numpad8"],
+            Action::MoveS => &["Down", "J", "Numpad2"],
+            Action::MoveW => &["Left", "H", "Numpad4"],
+            Action::MoveE => &["Right", "L", "Numpad6"],
+            Action::RunN => &["Shift+Up", "Shift+K", "Shift+Numpad8"],
+            Action::RunS => &["Shift+Down", "Shift+J", "Shift+Numpad2"],
+            Action::RunW => &["Shift+Left", "Shift+H", "Shift+Numpad4"],
+            Action::RunE => &["Shift+Right", "Shift+L", "Shift+Numpad6"],
+            Action::Wait => &["Period", "Numpad5"],
+            Action::Rest => &["R"],
+            Action::Pickup => &["G"],
+            Action::Inventory => &["I"],
+            Action::Equipment => &["E"],
+            Action::Aim => &["Z"],
+            Action::Look => &["X"],
+            Action::Descend => &["Shift+Period"],
+            Action::Ascend => &["Shift+Comma"],
+            Action::Teleport => &["Space"],
+            Action::Sneak => &["S"],
+            Action::FogStyle => &["F"],
+            Action::RenderMode => &["F2"],
+            Action::Save => &["F5"],
+            Action::Load => &["F9"],
+            Action::Help => &["F1", "Shift+Slash"],
+            Action::ScentOverlay => &["F3"],
+        }
+    }
+}
+
+/// A key, maybe with shift held down
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct KeyPress {
+    pub key: VirtualKeyCode,
+    pub shift: bool,
+}
+
+// the keys that can be bound, by the name they have in the keys file
+const BINDABLE_KEYS: [VirtualKeyCode; 70] = [
+    VirtualKeyCode::A,
+    VirtualKeyCode::B,
+    VirtualKeyCode::C,
+    VirtualKeyCode::D,
+    VirtualKeyCode::E,
+    VirtualKeyCode::F,
+    VirtualKeyCode::G,
+    VirtualKeyCode::H,
+    VirtualKeyCode::I,
+    VirtualKeyCode::J,
+    VirtualKeyCode::K,
+    VirtualKeyCode::L,
+    VirtualKeyCode::M,
+    VirtualKeyCode::N,
+    VirtualKeyCode::O,
+    VirtualKeyCode::P,
+    VirtualKeyCode::Q,
+    VirtualKeyCode::R,
+    VirtualKeyCode::S,
+    VirtualKeyCode::T,
+    VirtualKeyCode::U,
+    VirtualKeyCode::V,
+    VirtualKeyCode::W,
+    VirtualKeyCode::X,
+    VirtualKeyCode::Y,
+    VirtualKeyCode::Z,
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+    VirtualKeyCode::Numpad0,
+    VirtualKeyCode::Numpad1,
+    VirtualKeyCode::Numpad2,
+    VirtualKeyCode::Numpad3,
+    VirtualKeyCode::Numpad4,
+    VirtualKeyCode::Numpad5,
+    VirtualKeyCode::Numpad6,
+    VirtualKeyCode::Numpad7,
+    VirtualKeyCode::Numpad8,
+    VirtualKeyCode::Numpad9,
+    VirtualKeyCode::F1,
+    VirtualKeyCode::F2,
+    VirtualKeyCode::F3,
+    VirtualKeyCode::F4,
+    VirtualKeyCode::F5,
+    VirtualKeyCode::F6,
+    VirtualKeyCode::F7,
+    VirtualKeyCode::F8,
+    VirtualKeyCode::F9,
+    VirtualKeyCode::F10,
+    VirtualKeyCode::F11,
+    VirtualKeyCode::F12,
+    VirtualKeyCode::Up,
+    VirtualKeyCode::Down,
+    VirtualKeyCode::Left,
+    VirtualKeyCode::Right,
+    VirtualKeyCode::Space,
+    VirtualKeyCode::Period,
+    VirtualKeyCode::Comma,
+    VirtualKeyCode::Slash,
+    VirtualKeyCode::Semicolon,
+    VirtualKeyCode::Tab,
+    VirtualKeyCode::Return,
+    VirtualKeyCode::Back,
+];
+
+impl KeyPress {
+    /// Reads a key like `K`, `Up` or `Shift+Period`
+    fn parse(name: &str) -> Option<KeyPress> {
+        let (shift, key_name) = match name.strip_prefix("Shift+") {
+            Some(rest) => (true, rest),
+            None => (false, name),
+        };
+        BINDABLE_KEYS
+            .iter()
+            .find(|key| format!("{:?}", key) == key_name)
+            .map(|&key| KeyPress { key, shift })
+    }
+
+    /// How the key is shown on the help screen
+    pub fn label(&self) -> String {
+        let key = match self.key {
+            VirtualKeyCode::Period => ".".to_string(),
+            VirtualKeyCode::Comma => ",".to_string(),
+            VirtualKeyCode::Slash => "/".to_string(),
+            key => format!("{:?}", key),
+        };
+        match (self.shift, self.key) {
+            (true, VirtualKeyCode::Period) => ">".to_string(),
+            (true, VirtualKeyCode::Comma) => "<".to_string(),
+            (true, VirtualKeyCode::Slash) => "?".to_string(),
+            (true, _) => format!("Shift+{}", key),
+            (false, _) => key,
+        }
+    }
+}
+
+/// Which keys do what, read from the keys file. Every action can have more than one key
+pub struct KeyMap {
+    pub bindings: Vec<(Action, Vec<KeyPress>)>,
+}
+
+impl KeyMap {
+    /// Builds the keymap from the names of the keys, the actions missing get their default
+    fn from_names(names: &BTreeMap<Action, Vec<String>>) -> KeyMap {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let keys = match names.get(action) {
+                    Some(keys) => keys
+                        .iter()
+                        .filter_map(|name| {
+                            let key = KeyPress::parse(name);
+                            if key.is_none() {
+                                rltk::console::log(format!(
+                                    "Unknown key {} in {}",
+                                    name, KEYS_FILE
+                                ));
+                            }
+                            key
+                        })
+                        .collect(),
+                    None => default_keys(*action),
+                };
+                (*action, keys)
+            })
+            .collect();
+        let keymap = KeyMap { bindings };
+
+        // a key does only the first of its actions, the others are lost
+        for (i, (action, keys)) in keymap.bindings.iter().enumerate() {
+            for key in keys {
+                if let Some((first, _keys)) = keymap.bindings[..i]
+                    .iter()
+                    .find(|(_action, keys)| keys.contains(key))
+                {
+                    rltk::console::log(format!(
+                        "{} is bound to both {:?} and {:?} in {}, it does {:?}",
+                        key.label(),
+                        first,
+                        action,
+                        KEYS_FILE,
+                        first
+                    ));
+                }
+            }
+        }
+        keymap
+    }
+
+    /// The names of the keys, as they are written in the keys file
+    fn names(&self) -> BTreeMap<Action, Vec<String>> {
+        self.bindings
+            .iter()
+            .map(|(action, keys)| {
+                let names = keys
+                    .iter()
+                    .map(|k| {
+                        let key = format!("{:?}", k.key);
+                        if k.shift {
+                            format!("Shift+{}", key)
+                        } else {
+                            key
+                        }
+                    })
+                    .collect();
+                (*action, names)
+            })
+            .collect()
+    }
+
+    /// Reads the keys file, and writes it with the defaults if it's missing so it can be edited.
+    /// A broken file is left alone for the player to fix, the defaults are used meanwhile
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> KeyMap {
+        let defaults = || KeyMap::from_names(&BTreeMap::new());
+        match fs::read_to_string(KEYS_FILE) {
+            Ok(data) => match serde_json::from_str(&data) {
+                Ok(names) => KeyMap::from_names(&names),
+                Err(e) => {
+                    rltk::console::log(format!(
+                        "Unable to read {}: {}, using the default keys",
+                        KEYS_FILE, e
+                    ));
+                    defaults()
+                }
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let keymap = defaults();
+                keymap.save();
+                keymap
+            }
+            Err(e) => {
+                rltk::console::log(format!(
+                    "Unable to read {}: {}, using the default keys",
+                    KEYS_FILE, e
+                ));
+                defaults()
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> KeyMap {
+        KeyMap::from_names(&BTreeMap::new())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) {
+        let data = serde_json::to_string_pretty(&self.names()).expect("Unable to serialize keys");
+        if let Err(e) = fs::write(KEYS_FILE, data) {
+            rltk::console::log(format!("Unable to write {}: {}", KEYS_FILE, e));
+        }
+    }
+
+    /// The action bound to the key, if any
+    pub fn action(&self, key: VirtualKeyCode, shift: bool) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_action, keys)| keys.contains(&KeyPress { key, shift }))
+            .or_else(|| {
+                // a key bound without shift works with shift too, unless shift makes it another action
+                self.bindings
+                    .iter()
+                    .find(|(_action, keys)| shift && keys.contains(&KeyPress { key, shift: false }))
+            })
+            .map(|(action, _keys)| *action)
+    }
+
+    /// The keys bound to the action
+    pub fn keys(&self, action: Action) -> &[KeyPress] {
+        self.bindings
+            .iter()
+            .find(|(a, _keys)| *a == action)
+            .map_or(&[], |(_a, keys)| keys.as_slice())
+    }
+}
+
+fn default_keys(action: Action) -> Vec<KeyPress> {
+    action
+        .default_keys()
+        .iter()
+        .filter_map(|name| KeyPress::parse(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: VirtualKeyCode, shift: bool) -> KeyPress {
+        KeyPress { key, shift }
+    }
+
+    #[test]
+    fn parse_plain_and_shifted_keys() {
+        assert_eq!(KeyPress::parse("K"), Some(press(VirtualKeyCode::K, false)));
+        assert_eq!(
+            KeyPress::parse("Numpad8"),
+            Some(press(VirtualKeyCode::Numpad8, false))
+        );
+        assert_eq!(
+            KeyPress::parse("Shift+Period"),
+            Some(press(VirtualKeyCode::Period, true))
+        );
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        assert_eq!(KeyPress::parse(""), None);
+        assert_eq!(KeyPress::parse("Shift+"), None);
+        assert_eq!(KeyPress::parse("k"), None);
+        assert_eq!(KeyPress::parse("shift+K"), None);
+        assert_eq!(KeyPress::parse("Ctrl+K"), None);
+        assert_eq!(KeyPress::parse("Escape"), None); // not one of the keys that can be bound
+    }
+
+    #[test]
+    fn action_of_the_default_keys() {
+        let keymap = KeyMap::from_names(&BTreeMap::new());
+        assert_eq!(keymap.action(VirtualKeyCode::K, false), Some(Action::MoveN));
+        assert_eq!(keymap.action(VirtualKeyCode::Up, true), Some(Action::RunN));
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Period, false),
+            Some(Action::Wait)
+        );
+        assert_eq!(keymap.action(VirtualKeyCode::Q, false), None);
+    }
+
+    #[test]
+    fn shift_falls_back_to_the_plain_key() {
+        let keymap = KeyMap::from_names(&BTreeMap::new());
+        // nothing is bound to Shift+R, so it's R
+        assert_eq!(keymap.action(VirtualKeyCode::R, true), Some(Action::Rest));
+        // but shift wins when it is bound to something else
+        assert_eq!(
+            keymap.action(VirtualKeyCode::Period, true),
+            Some(Action::Descend)
+        );
+        assert_eq!(keymap.action(VirtualKeyCode::K, true), Some(Action::RunN));
+        // and a shifted key doesn't work without shift
+        assert_eq!(keymap.action(VirtualKeyCode::Slash, false), None);
+        assert_eq!(keymap.action(VirtualKeyCode::Comma, false), None);
+        assert_eq!(keymap.action(VirtualKeyCode::Q, true), None);
+    }
+
+    #[test]
+    fn keys_file_overrides_the_defaults() {
+        let mut names = BTreeMap::new();
+        names.insert(
+            Action::Rest,
+            vec!["Shift+R".to_string(), "Bogus".to_string()],
+        );
+        let keymap = KeyMap::from_names(&names);
+        assert_eq!(keymap.keys(Action::Rest), &[press(VirtualKeyCode::R, true)]);
+        assert_eq!(keymap.action(VirtualKeyCode::R, false), None);
+        assert_eq!(keymap.action(VirtualKeyCode::R, true), Some(Action::Rest));
+        // the actions left out keep their keys
+        assert_eq!(
+            keymap.action(VirtualKeyCode::G, false),
+            Some(Action::Pickup)
+        );
+    }
+}
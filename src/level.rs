@@ -0,0 +1,378 @@
+use super::{
+    overworld, prefab, saveload_system, spawner, vault, Equipped, GameLog, InBackpack, Map, Player,
+    PlayerRoom, Position, Rect, RexAssets, SerializeMe, TileType, Viewshed,
+};
+use crate::corridors::CorridorStyle;
+use rltk::{Point, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs::saveload::SimpleMarker;
+
+/// How many levels a dungeon goes down, the last one has no stairs going further
+const DUNGEON_DEPTH: i32 = 5;
+
+/// The levels the player isn't on: the overworld, put aside while the player is down in a
+/// dungeon, and the dungeon levels already seen, to find them again as they were left
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct Overworld {
+    pub map: Option<Map>,
+    #[serde(default)]
+    pub entities: String, // what was on it, written as in the savegame
+    pub entrance: (i32, i32), // where the player went down
+    #[serde(default)]
+    pub depth: i32, // how many levels below the overworld the player is
+    #[serde(default)]
+    pub dungeons: Vec<DungeonLevel>,
+}
+
+/// A dungeon level the player went away from
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DungeonLevel {
+    pub entrance: (i32, i32), // the stairs on the overworld the dungeon starts from
+    pub depth: i32,
+    pub map: Map,
+    pub entities: String,
+}
+
+/// How many random tiles are tried for a monster before giving up on it
+const MONSTER_SPOT_TRIES: i32 = 10;
+
+/// A random tile of the room, if nothing solid is there
+fn random_floor(map: &Map, room: &Rect, rng: &mut RandomNumberGenerator) -> Option<(i32, i32)> {
+    let tile = room.random_point(rng);
+    (!map.tiles[map.xy_idx(tile.x, tile.y)].is_solid()).then_some((tile.x, tile.y))
+}
+
+/// A new dungeon level, with the stairs back up where the player arrives, the stairs further
+/// down if it isn't the last one, and its monsters and items already spawned.
+/// Returns the map and where the player starts
+pub fn new_dungeon(ecs: &mut World, rng: &mut RandomNumberGenerator, depth: i32) -> (Map, Point) {
+    let corridors = CorridorStyle::random(rng);
+    let (mut map, start, mut prefab_spawns) = prefab::build_level(rng, corridors);
+    let stairs = map.xy_idx(start.x, start.y);
+    map.tiles[stairs] = TileType::UpStairs;
+
+    // a hand-made vault, if there is room for it
+    {
+        let rex = ecs.fetch::<RexAssets>();
+        prefab_spawns.extend(vault::stamp_random_vault(&mut map, rng, &rex.vaults));
+    }
+    // the way further down is in the last room, away from the way up
+    if depth < DUNGEON_DEPTH {
+        let down = map
+            .rooms
+            .last()
+            .and_then(|room| map.room_spot(room))
+            .filter(|spot| *spot != start);
+        if let Some(down) = down {
+            let idx = map.xy_idx(down.x, down.y);
+            map.tiles[idx] = TileType::DownStairs;
+        }
+    }
+    let prefab_spawns_count = prefab_spawns.len();
+    // the tiles with a monster already, two can't stand on the same one
+    let mut taken: Vec<(i32, i32)> = prefab_spawns.iter().map(|(x, y, _)| (*x, *y)).collect();
+    for (i, (x, y, marker)) in prefab_spawns.into_iter().enumerate() {
+        if !spawner::spawn_marker(ecs, x, y, marker, map.rooms.len() + i, rng) {
+            rltk::console::log(format!("Unknown prefab marker {}", marker));
+        }
+    }
+
+    // monsters and items, as many as the theme of each room says
+    let mut next_name = map.rooms.len() + prefab_spawns_count;
+    let chance = |rng: &mut RandomNumberGenerator, n: i32| n > 0 && rng.roll_dice(1, n) == 1;
+    for (i, room) in map.rooms.iter().enumerate() {
+        let table = map.room_theme(i).spawn_table();
+        // creo un'entita monster per ogni stanza, non in quella del player
+        if i > 0 {
+            for m in 0..table.monsters {
+                let (x, y) = room.center();
+                let spot = if m == 0 && !map.tiles[map.xy_idx(x, y)].is_solid() {
+                    Some((x, y))
+                } else {
+                    (0..MONSTER_SPOT_TRIES)
+                        .filter_map(|_| random_floor(&map, room, rng))
+                        .find(|spot| !taken.contains(spot))
+                };
+                if let Some((x, y)) = spot.filter(|spot| !taken.contains(spot)) {
+                    spawner::monster(ecs, x, y, next_name, rng);
+                    taken.push((x, y));
+                    next_name += 1;
+                }
+            }
+        }
+        // something to wear lying around, sometimes
+        if chance(rng, table.equipment) {
+            if let Some((x, y)) = random_floor(&map, room, rng) {
+                spawner::random_equipment(ecs, x, y, rng);
+            }
+        }
+        // and a potion here and there
+        if chance(rng, table.potions) {
+            if let Some((x, y)) = random_floor(&map, room, rng) {
+                spawner::random_potion(ecs, x, y, rng);
+            }
+        }
+        // something to eat
+        if chance(rng, table.rations) {
+            if let Some((x, y)) = random_floor(&map, room, rng) {
+                spawner::ration(ecs, x, y);
+            }
+        }
+        // traps, but not where the player starts
+        if i > 0 && chance(rng, table.traps) {
+            if let Some((x, y)) = random_floor(&map, room, rng) {
+                spawner::random_trap(ecs, x, y, rng);
+            }
+        }
+    }
+    // a torch on the wall of some rooms
+    for room in map.rooms.iter() {
+        if rng.roll_dice(1, 3) > 1 {
+            continue;
+        }
+        if let Some(corner) = room.interior().next() {
+            spawner::torch(ecs, corner.x, corner.y);
+        }
+    }
+
+    (map, start)
+}
+
+/// Goes down into a dungeon, or deeper, or back up towards the overworld, if the player is
+/// on the stairs going that way. Returns `false` if there are none here
+pub fn take_stairs(ecs: &mut World, down: bool) -> bool {
+    let player_pos = *ecs.fetch::<Point>();
+    let tile = {
+        let map = ecs.fetch::<Map>();
+        map.tiles[map.xy_idx(player_pos.x, player_pos.y)]
+    };
+    let going = match tile {
+        TileType::DownStairs if down => 1,
+        TileType::UpStairs if !down => -1,
+        _ => {
+            ecs.write_resource::<GameLog>().log(if down {
+                "There are no stairs going down here"
+            } else {
+                "There are no stairs going up here"
+            });
+            return false;
+        }
+    };
+
+    let mut rng = ecs
+        .remove::<RandomNumberGenerator>()
+        .unwrap_or_else(RandomNumberGenerator::new);
+    let mut depth = ecs.fetch::<Overworld>().depth;
+    if going < 0 && depth == 0 {
+        // a savegame from before the levels were counted, the player is in the first one
+        depth = 1;
+    }
+    let mut overworld_spawns = Vec::new();
+    if going < 0 && depth == 1 && ecs.fetch::<Overworld>().map.is_none() {
+        // a savegame from before the overworld was kept: the player comes out on a new one
+        let (map, landing, spawns) = overworld::build_overworld(&mut rng);
+        let entrance = find_tile(&map, TileType::DownStairs).unwrap_or(landing);
+        let mut overworld = ecs.write_resource::<Overworld>();
+        overworld.map = Some(map);
+        overworld.entities = String::new();
+        overworld.entrance = (entrance.x, entrance.y);
+        overworld_spawns = spawns;
+    }
+
+    leave_level(ecs, depth, player_pos);
+    let depth = depth + going;
+    ecs.write_resource::<Overworld>().depth = depth;
+    let (map, start) = if depth == 0 {
+        let (map, entities, entrance) = {
+            let mut overworld = ecs.write_resource::<Overworld>();
+            let map = overworld.map.take().expect("The overworld is missing");
+            (
+                map,
+                std::mem::take(&mut overworld.entities),
+                overworld.entrance,
+            )
+        };
+        unstash(ecs, &entities);
+        for (i, (x, y, marker)) in overworld_spawns.into_iter().enumerate() {
+            spawner::spawn_marker(ecs, x, y, marker, i, &mut rng);
+        }
+        (map, Point::new(entrance.0, entrance.1))
+    } else {
+        match take_dungeon_level(ecs, depth) {
+            Some(level) => {
+                unstash(ecs, &level.entities);
+                // coming from above on the stairs up, from below on the stairs down
+                let stairs = if going > 0 {
+                    TileType::UpStairs
+                } else {
+                    TileType::DownStairs
+                };
+                let start = find_tile(&level.map, stairs)
+                    .unwrap_or_else(|| Point::from(level.map.rooms[0].center()));
+                (level.map, start)
+            }
+            None => new_dungeon(ecs, &mut rng, depth),
+        }
+    };
+    ecs.insert(rng);
+    enter_level(ecs, map, start);
+
+    ecs.write_resource::<GameLog>().log(match (going, depth) {
+        (_, 0) => "You come back out in the sunlight",
+        (1, 1) => "You climb down into the dark",
+        (1, _) => "You go further down",
+        _ => "You climb back up",
+    });
+    true
+}
+
+/// Puts aside the map the player is on, with everything on it but the player and
+/// what they carry. What isn't saved with the game, like the particles, is left behind
+fn leave_level(ecs: &mut World, depth: i32, player_pos: Point) {
+    let mut to_stash = Vec::new();
+    let mut to_delete = Vec::new();
+    {
+        let entities = ecs.entities();
+        let players = ecs.read_storage::<Player>();
+        let backpacks = ecs.read_storage::<InBackpack>();
+        let equipped = ecs.read_storage::<Equipped>();
+        let saved = ecs.read_storage::<SimpleMarker<SerializeMe>>();
+        let player = *ecs.fetch::<Entity>();
+        for entity in entities.join() {
+            let carried = backpacks.get(entity).is_some_and(|b| b.owner == player)
+                || equipped.get(entity).is_some_and(|e| e.owner == player);
+            if players.get(entity).is_some() || carried {
+                continue;
+            }
+            if saved.contains(entity) {
+                to_stash.push(entity);
+            } else {
+                to_delete.push(entity);
+            }
+        }
+    }
+    for entity in to_delete {
+        ecs.delete_entity(entity).expect("Unable to delete entity");
+    }
+    let entities = saveload_system::stash_entities(ecs, &to_stash);
+    let map = (*ecs.fetch::<Map>()).clone();
+
+    let mut overworld = ecs.write_resource::<Overworld>();
+    if depth == 0 {
+        overworld.map = Some(map);
+        overworld.entities = entities;
+        overworld.entrance = (player_pos.x, player_pos.y);
+    } else {
+        let entrance = overworld.entrance;
+        overworld.dungeons.push(DungeonLevel {
+            entrance,
+            depth,
+            map,
+            entities,
+        });
+    }
+}
+
+/// Takes out the level of the dungeon the player is in, if they have been there already
+fn take_dungeon_level(ecs: &mut World, depth: i32) -> Option<DungeonLevel> {
+    let mut overworld = ecs.write_resource::<Overworld>();
+    let entrance = overworld.entrance;
+    let i = overworld
+        .dungeons
+        .iter()
+        .position(|level| level.entrance == entrance && level.depth == depth)?;
+    Some(overworld.dungeons.swap_remove(i))
+}
+
+/// Brings back the entities of a level, the ones that can't be read are lost
+fn unstash(ecs: &mut World, entities: &str) {
+    if entities.is_empty() {
+        return;
+    }
+    if let Err(e) = saveload_system::unstash_entities(ecs, entities) {
+        rltk::console::log(format!("Unable to bring back the level: {}", e));
+    }
+}
+
+/// The first tile of the kind, going row by row
+fn find_tile(map: &Map, tile: TileType) -> Option<Point> {
+    let idx = map.tiles.iter().position(|t| *t == tile)?;
+    Some(Point::new(idx as i32 % map.width, idx as i32 / map.width))
+}
+
+/// Puts the player on the new map
+fn enter_level(ecs: &mut World, mut map: Map, start: Point) {
+    // a map out of the savegame has nothing indexed
+    map.tile_content = vec![Vec::new(); map.tiles.len()];
+    ecs.insert(map);
+    *ecs.write_resource::<Point>() = start;
+    *ecs.write_resource::<PlayerRoom>() = PlayerRoom::default();
+    let player = *ecs.fetch::<Entity>();
+    if let Some(pos) = ecs.write_storage::<Position>().get_mut(player) {
+        pos.x = start.x;
+        pos.y = start.y;
+    }
+    if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(player) {
+        viewshed.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{register_components, ParticleLifetime, Projectile, Renderable};
+    use spawner::MonsterKind;
+
+    #[test]
+    fn stashed_level_comes_back_without_particles() {
+        let mut ecs = World::new();
+        register_components(&mut ecs);
+        ecs.insert(Map::new_blank());
+        ecs.insert(Overworld::default());
+        let player = spawner::player(&mut ecs, 5, 5);
+        ecs.insert(player);
+        spawner::monster_of_kind(&mut ecs, 6, 5, 0, MonsterKind::Vosklamati, false);
+        spawner::ration(&mut ecs, 7, 5);
+        let glyph = || Renderable {
+            glyph: rltk::to_cp437('*'),
+            fg: rltk::RGB::named(rltk::WHITE),
+            bg: rltk::RGB::named(rltk::BLACK),
+            render_order: 0,
+            sprite: None,
+        };
+        ecs.create_entity()
+            .with(Position { x: 6, y: 5 })
+            .with(glyph())
+            .with(ParticleLifetime { lifetime_ms: 200. })
+            .build();
+        ecs.create_entity()
+            .with(Position { x: 7, y: 5 })
+            .with(glyph())
+            .with(Projectile {
+                path: vec![Point::new(8, 5)],
+                step: 0,
+                timer: 0.,
+            })
+            .build();
+        ecs.maintain();
+
+        leave_level(&mut ecs, 0, Point::new(5, 5));
+        ecs.maintain();
+        assert_eq!(ecs.entities().join().count(), 1);
+
+        let entities = std::mem::take(&mut ecs.write_resource::<Overworld>().entities);
+        unstash(&mut ecs, &entities);
+        ecs.maintain();
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        let saved = ecs.read_storage::<SimpleMarker<SerializeMe>>();
+        assert_eq!((&entities, &positions).join().count(), 3);
+        assert!((&entities, &positions)
+            .join()
+            .all(|(e, _)| saved.contains(e)));
+        assert_eq!(ecs.read_storage::<ParticleLifetime>().join().count(), 0);
+        assert_eq!(ecs.read_storage::<Projectile>().join().count(), 0);
+        assert_eq!(ecs.read_storage::<Player>().join().count(), 1);
+    }
+}
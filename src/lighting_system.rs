@@ -0,0 +1,49 @@
+use super::{LightSource, Map, Player, Position, Viewshed};
+use rltk::{field_of_view, Point, RGB};
+use specs::prelude::*;
+
+/// The light of the sun on the overworld
+const DAYLIGHT: f32 = 0.9;
+
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, lights, player, mut viewsheds) = data;
+
+        let ambient = if map.outdoors { DAYLIGHT } else { 0. };
+        let mut light = vec![RGB::from_f32(ambient, ambient, ambient); map.tiles.len()];
+
+        // every light fades out linearly with the distance, and stops at the walls
+        for (pos, source) in (&positions, &lights).join() {
+            let centre = Point::new(pos.x, pos.y);
+            let lit_tiles = field_of_view(centre, source.range, &*map);
+            for tile in lit_tiles.iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(centre, *tile);
+                let intensity = (1.0 - distance / source.range as f32).max(0.);
+                let idx = map.xy_idx(tile.x, tile.y);
+                let sum = light[idx] + source.colour * intensity;
+                light[idx] = RGB::from_f32(sum.r.min(1.), sum.g.min(1.), sum.b.min(1.));
+            }
+        }
+
+        // what the player sees depends on the light, so look again when it changes
+        if light != map.light {
+            map.light = light;
+            for (viewshed, _player) in (&mut viewsheds, &player).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
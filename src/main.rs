@@ -12,171 +12,577 @@ mod monster;
 use monster::MonsterSystem;
 mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
+mod lighting_system;
+use lighting_system::LightingSystem;
+mod hearing_system;
+use hearing_system::HearingSystem;
+pub use hearing_system::NoiseEvents;
+mod initiative_system;
+use initiative_system::InitiativeSystem;
+pub use initiative_system::TURN_COST;
 
+mod melee_combat_system;
+use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
+mod damage_system;
+use damage_system::DamageSystem;
+mod inventory_system;
+use inventory_system::{ItemCollectionSystem, ItemEquipSystem, ItemUseSystem};
+mod hunger_system;
+use hunger_system::HungerSystem;
+pub use hunger_system::WELL_FED_TURNS;
+mod trigger_system;
+use trigger_system::TriggerSystem;
+mod scent_system;
+use scent_system::{ScentOverlay, ScentSystem};
+mod swimming_system;
+use swimming_system::SwimmingSystem;
+mod status_effect_system;
+use status_effect_system::StatusEffectSystem;
+mod particle_system;
+use particle_system::ParticleSpawnSystem;
+pub use particle_system::{ParticleBuilder, HIT_PARTICLE_MS, PARTICLE_RENDER_ORDER};
+mod gamelog;
+pub use gamelog::GameLog;
+mod gui;
+mod progression;
+pub use progression::*;
+mod keymap;
+pub use keymap::{Action, KeyMap};
+mod config;
+pub use config::Config;
+mod tileset;
+pub use tileset::*;
+mod room_system;
+use room_system::{PlayerRoom, RoomSystem};
+mod room_theme;
+pub use room_theme::RoomTheme;
+mod rex_assets;
+pub use rex_assets::RexAssets;
+mod corridors;
+mod level;
+pub use level::Overworld;
+mod overworld;
+mod prefab;
+mod saveload_system;
+mod spawner;
+mod vault;
+
+mod autopilot;
+use autopilot::AutoPilot;
 mod player;
-use rltk::{to_cp437, GameState, Point, RandomNumberGenerator, Rltk, VirtualKeyCode, RGB};
+use rltk::{GameState, Point, RandomNumberGenerator, Rltk, RGB};
 use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
 
 pub struct State {
     pub ecs: World,
-    pub runstate: RunState,
 }
 impl GameState for State {
     // this gets called at each frame - it's kind of the renderer I guess
     fn tick(&mut self, ctx: &mut Rltk) {
-        read_input(self, ctx);
-
-        if self.runstate == RunState::Running {
-            self.run_systems();
+        // the menus are drawn on top of the game
+        ctx.set_active_console(TILES_CONSOLE);
+        ctx.cls();
+        ctx.set_active_console(ASCII_CONSOLE);
+        ctx.cls();
+        let runstate = *self.ecs.fetch::<RunState>();
 
-            ctx.cls();
+        if runstate != RunState::Title {
+            particle_system::update_particles(&mut self.ecs, ctx.frame_time_ms);
             draw_map(&self.ecs, ctx);
+            draw_entities(&self.ecs, ctx);
+            draw_travel_path(&self.ecs, ctx);
+            scent_system::draw_scent(&self.ecs, ctx);
+            gui::draw_ui(&self.ecs, ctx);
+        }
 
-            let positions = self.ecs.read_storage::<Position>();
-            let renderables = self.ecs.read_storage::<Renderable>();
-            let names = self.ecs.read_storage::<Name>();
-            let map = self.ecs.fetch::<Map>();
+        match runstate {
+            RunState::Title => match gui::title_screen(&self.ecs, ctx) {
+                Some(gui::TitleSelection::NewGame) => {
+                    *self.ecs.write_resource::<RunState>() = RunState::Running;
+                }
+                Some(gui::TitleSelection::LoadGame) => {
+                    let message = match saveload_system::load_game(&mut self.ecs) {
+                        Ok(()) => "Game loaded".to_string(),
+                        Err(e) => e,
+                    };
+                    self.ecs.write_resource::<GameLog>().log(message);
+                    *self.ecs.write_resource::<RunState>() = RunState::Running;
+                }
+                Some(gui::TitleSelection::Quit) => ctx.quit(),
+                None => {}
+            },
+            RunState::Paused => {
+                let newrunstate = if autopilot::is_active(&self.ecs) {
+                    // any key stops the player doing things by themselves
+                    if ctx.key.is_some() {
+                        autopilot::stop(&mut self.ecs);
+                        RunState::Paused
+                    } else {
+                        autopilot_step(&mut self.ecs)
+                    }
+                } else {
+                    read_input(self, ctx)
+                };
+                *self.ecs.write_resource::<RunState>() = newrunstate;
+            }
+            RunState::Running => {
+                // keep ticking the world until the player has enough energy to act
+                while *self.ecs.fetch::<RunState>() == RunState::Running {
+                    self.run_systems();
+                    damage_system::delete_the_dead(&mut self.ecs);
 
-            // draw entities with a renderable compoennt attached
-            for (pos, render, _name) in (&positions, &renderables, &names).join() {
-                let idx = map.xy_idx(pos.x, pos.y);
-                if map.visible_tiles[idx] {
-                    ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph);
+                    // the level up screen waits for the player, then the world goes on
+                    if progression::check_level_up(&mut self.ecs) {
+                        *self.ecs.write_resource::<RunState>() = RunState::LevelUp;
+                    }
+                }
+            }
+            RunState::Targeting { target } => {
+                let mut selected = target;
+                let newrunstate = match gui::ranged_target(&self.ecs, ctx, &mut selected) {
+                    (gui::ItemMenuResult::Cancel, _) => RunState::Paused,
+                    (gui::ItemMenuResult::Selected, Some(point)) => {
+                        let cost = player::shoot(&mut self.ecs, point);
+                        player::spend_energy(&mut self.ecs, cost);
+                        RunState::Running
+                    }
+                    _ => RunState::Targeting { target: selected },
+                };
+                *self.ecs.write_resource::<RunState>() = newrunstate;
+            }
+            RunState::Looking { x, y } => {
+                let mut cursor = Point::new(x, y);
+                let newrunstate = match gui::look(&self.ecs, ctx, &mut cursor) {
+                    gui::ItemMenuResult::Cancel => RunState::Paused,
+                    _ => RunState::Looking {
+                        x: cursor.x,
+                        y: cursor.y,
+                    },
+                };
+                *self.ecs.write_resource::<RunState>() = newrunstate;
+            }
+            RunState::LevelUp => {
+                if let Some(perk) = gui::level_up_menu(ctx) {
+                    progression::apply_perk(&mut self.ecs, perk);
+                    *self.ecs.write_resource::<RunState>() = RunState::Running;
+                }
+            }
+            RunState::ShowInventory => {
+                let newrunstate = match gui::show_inventory(&self.ecs, ctx) {
+                    (gui::ItemMenuResult::Cancel, _) => RunState::Paused,
+                    (gui::ItemMenuResult::Selected, Some(item)) => {
+                        let cost = player::use_item(&mut self.ecs, item);
+                        player::spend_energy(&mut self.ecs, cost);
+                        RunState::Running
+                    }
+                    _ => RunState::ShowInventory,
+                };
+                *self.ecs.write_resource::<RunState>() = newrunstate;
+            }
+            RunState::ShowEquipment => {
+                let newrunstate = match gui::show_equipment(&self.ecs, ctx) {
+                    (gui::ItemMenuResult::Cancel, _) => RunState::Paused,
+                    (gui::ItemMenuResult::Selected, Some(item)) => {
+                        // equipping what is already worn takes it off
+                        let cost = player::equip_item(&mut self.ecs, item);
+                        player::spend_energy(&mut self.ecs, cost);
+                        RunState::Running
+                    }
+                    _ => RunState::ShowEquipment,
+                };
+                *self.ecs.write_resource::<RunState>() = newrunstate;
+            }
+            RunState::ShowHelp => {
+                if gui::help_screen(&self.ecs, ctx) {
+                    *self.ecs.write_resource::<RunState>() = RunState::Paused;
+                }
+            }
+            RunState::GameOver => {
+                if gui::game_over(ctx) {
+                    ctx.quit();
                 }
             }
-            self.runstate = RunState::Paused;
-        } else {
-            self.runstate = read_input(self, ctx);
         }
     }
 }
 
+fn draw_entities(ecs: &World, ctx: &mut Rltk) {
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let map = ecs.fetch::<Map>();
+
+    let last_seen = ecs.read_storage::<LastSeen>();
+    let fog = *ecs.fetch::<FogStyle>();
+
+    // draw entities with a renderable compoennt attached, the items below the monsters
+    let hidden = ecs.read_storage::<Hidden>();
+    let mut data = (&positions, &renderables, !&hidden)
+        .join()
+        .map(|(pos, render, _hidden)| (pos, render))
+        .collect::<Vec<_>>();
+    data.sort_by_key(|(_pos, render)| std::cmp::Reverse(render.render_order));
+    let mode = ecs.fetch::<Config>().render_mode;
+    for (pos, render) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if !map.visible_tiles[idx] {
+            continue;
+        }
+        match render.sprite.filter(|_| mode == RenderMode::Tiles) {
+            Some(sprite) => {
+                ctx.set_active_console(TILES_CONSOLE);
+                let tint = shade(idx, &map, fog, RGB::named(rltk::WHITE));
+                ctx.set(pos.x, pos.y, tint, RGB::named(rltk::BLACK), sprite);
+                ctx.set_active_console(ASCII_CONSOLE);
+            }
+            None => ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph),
+        }
+    }
+
+    // ghosts of the monsters out of sight, where the player last saw them
+    for (pos, render, seen) in (&positions, &renderables, &last_seen).join() {
+        let idx = map.xy_idx(seen.x, seen.y);
+        if map.visible_tiles[map.xy_idx(pos.x, pos.y)] || map.visible_tiles[idx] {
+            continue;
+        }
+        match render.sprite.filter(|_| mode == RenderMode::Tiles) {
+            Some(sprite) => {
+                ctx.set_active_console(TILES_CONSOLE);
+                let tint = shade(idx, &map, fog, RGB::named(rltk::WHITE));
+                ctx.set(seen.x, seen.y, tint, RGB::named(rltk::BLACK), sprite);
+                ctx.set_active_console(ASCII_CONSOLE);
+            }
+            None => {
+                let (_glyph, _fg, bg) = tile_glyph(idx, &map, fog);
+                ctx.set(seen.x, seen.y, fog.apply(render.fg), bg, render.glyph);
+            }
+        }
+    }
+}
+
+/// Highlights the tiles the player is still going to walk through
+fn draw_travel_path(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    for idx in autopilot::travel_path(ecs) {
+        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+        ctx.set_bg(x, y, RGB::named(rltk::DARK_CYAN));
+    }
+}
+
 // qui per leggere la tastiera
 pub fn read_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    match ctx.key {
+    // clicking on the map walks there
+    if ctx.left_click {
+        let (x, y) = ctx.mouse_pos();
+        let map = gs.ecs.fetch::<Map>();
+        if x >= 0 && x < map.width && y >= 0 && y < map.height {
+            drop(map);
+            autopilot::travel(&mut gs.ecs, Point::new(x, y));
+            return autopilot_step(&mut gs.ecs);
+        }
+    }
+
+    let action = match ctx.key {
         None => return RunState::Paused, // nothing happened
-        Some(key) => match key {
+        Some(key) => gs.ecs.fetch::<KeyMap>().action(key, ctx.shift),
+    };
+    let cost = match action {
+        None => return RunState::Paused, // not bound to anything
+        Some(action) => match action {
             // movement
-            VirtualKeyCode::Up | VirtualKeyCode::K => player::try_move_player(0, -1, &mut gs.ecs),
-            VirtualKeyCode::Down | VirtualKeyCode::J => player::try_move_player(0, 1, &mut gs.ecs),
-            VirtualKeyCode::Left | VirtualKeyCode::H => player::try_move_player(-1, 0, &mut gs.ecs),
-            VirtualKeyCode::Right | VirtualKeyCode::L => player::try_move_player(1, 0, &mut gs.ecs),
+            Action::MoveN => player::try_move_player(0, -1, &mut gs.ecs),
+            Action::MoveS => player::try_move_player(0, 1, &mut gs.ecs),
+            Action::MoveW => player::try_move_player(-1, 0, &mut gs.ecs),
+            Action::MoveE => player::try_move_player(1, 0, &mut gs.ecs),
+
+            // up and down the stairs
+            Action::Descend | Action::Ascend => {
+                if !level::take_stairs(&mut gs.ecs, action == Action::Descend) {
+                    return RunState::Paused;
+                }
+                player::STAIRS_COST
+            }
+
+            // keep going until something interesting shows up
+            Action::RunN | Action::RunS | Action::RunW | Action::RunE => {
+                let (dx, dy) = match action {
+                    Action::RunN => (0, -1),
+                    Action::RunS => (0, 1),
+                    Action::RunW => (-1, 0),
+                    _ => (1, 0),
+                };
+                autopilot::run(&mut gs.ecs, dx, dy);
+                return autopilot_step(&mut gs.ecs);
+            }
+
+            // let a turn go by, or many of them
+            Action::Wait => player::WAIT_COST,
+            Action::Rest => {
+                autopilot::rest(&mut gs.ecs);
+                return autopilot_step(&mut gs.ecs);
+            }
+
+            // switch how the fog of war looks, it's free
+            Action::FogStyle => {
+                let mut fog = gs.ecs.write_resource::<FogStyle>();
+                *fog = fog.next();
+                0
+            }
+
+            // sprites or glyphs, remembered for the next time
+            Action::RenderMode => {
+                let mut config = gs.ecs.write_resource::<Config>();
+                config.render_mode = config.render_mode.next();
+                config.save();
+                0
+            }
+
+            // moving carefully makes less noise
+            Action::Sneak => {
+                player::toggle_sneak(&mut gs.ecs);
+                0
+            }
+
+            // saving and loading don't take a turn either
+            Action::Save => {
+                let message = match saveload_system::save_game(&mut gs.ecs) {
+                    Ok(()) => "Game saved".to_string(),
+                    Err(e) => e,
+                };
+                gs.ecs.write_resource::<GameLog>().log(message);
+                0
+            }
+            Action::Load => {
+                if saveload_system::does_save_exist() {
+                    match saveload_system::load_game(&mut gs.ecs) {
+                        Ok(()) => {
+                            gs.ecs.write_resource::<GameLog>().log("Game loaded");
+                            // look around again with the loaded map
+                            return RunState::Running;
+                        }
+                        Err(e) => gs.ecs.write_resource::<GameLog>().log(e),
+                    }
+                }
+                0
+            }
+
+            // items
+            Action::Pickup => player::get_item(&mut gs.ecs),
+            Action::Inventory => return RunState::ShowInventory,
+            Action::Equipment => return RunState::ShowEquipment,
+
+            // aim with the bow, if there is one
+            Action::Aim => {
+                let player = *gs.ecs.fetch::<Entity>();
+                if gui::player_ranged_weapon(&gs.ecs, player).is_some() {
+                    return RunState::Targeting { target: 0 };
+                }
+                gs.ecs
+                    .write_resource::<GameLog>()
+                    .log("You have nothing to shoot with");
+                0
+            }
+
+            // look around, it's free
+            Action::Look => {
+                let player_pos = *gs.ecs.fetch::<Point>();
+                return RunState::Looking {
+                    x: player_pos.x,
+                    y: player_pos.y,
+                };
+            }
 
             // teleport the player to a random room
-            VirtualKeyCode::Space => player::move_to_random_room(&mut gs.ecs),
+            Action::Teleport => player::move_to_random_room(&mut gs.ecs),
 
-            // matchall
-            _ => return RunState::Paused,
+            Action::Help => return RunState::ShowHelp,
+
+            // what the trackers smell, for debugging
+            Action::ScentOverlay => {
+                let mut overlay = gs.ecs.write_resource::<ScentOverlay>();
+                overlay.visible = !overlay.visible;
+                0
+            }
         },
+    };
+
+    // walking into the water or changing settings doesn't take a turn
+    if cost == 0 {
+        return RunState::Paused;
     }
+    player::spend_energy(&mut gs.ecs, cost);
     RunState::Running
 }
 
+/// Takes the next step of what the player is doing by themselves
+fn autopilot_step(ecs: &mut World) -> RunState {
+    match autopilot::step(ecs) {
+        Some(cost) => {
+            player::spend_energy(ecs, cost);
+            RunState::Running
+        }
+        None => RunState::Paused,
+    }
+}
+
 impl State {
     fn run_systems(&mut self) {
+        let mut initiative = InitiativeSystem {};
         let mut vis = VisibilitySystem {};
         let mut rand_mov = MonsterSystem {};
         let mut map_indexing = MapIndexingSystem {};
+        let mut lighting = LightingSystem {};
+        let mut hearing = HearingSystem {};
+        let mut melee = MeleeCombatSystem {};
+        let mut ranged = RangedCombatSystem {};
+        let mut damage = DamageSystem {};
+        let mut pickup = ItemCollectionSystem {};
+        let mut equip = ItemEquipSystem {};
+        let mut use_items = ItemUseSystem {};
+        let mut status = StatusEffectSystem {};
+        let mut triggers = TriggerSystem {};
+        let mut hunger = HungerSystem {};
+        let mut swimming = SwimmingSystem {};
+        let mut scent = ScentSystem {};
+        let mut particles = ParticleSpawnSystem {};
+        let mut rooms = RoomSystem {};
+        initiative.run_now(&self.ecs);
+        hunger.run_now(&self.ecs);
+        swimming.run_now(&self.ecs);
+        scent.run_now(&self.ecs);
+        status.run_now(&self.ecs);
+        hearing.run_now(&self.ecs);
         rand_mov.run_now(&self.ecs);
+        pickup.run_now(&self.ecs);
+        equip.run_now(&self.ecs);
+        use_items.run_now(&self.ecs);
+        melee.run_now(&self.ecs);
+        ranged.run_now(&self.ecs);
         map_indexing.run_now(&self.ecs);
+        triggers.run_now(&self.ecs);
+        damage.run_now(&self.ecs);
+        lighting.run_now(&self.ecs);
         vis.run_now(&self.ecs);
+        rooms.run_now(&self.ecs);
+        particles.run_now(&self.ecs);
         self.ecs.maintain();
     }
 }
 
+/// Every component, and the allocator of the savegame markers
+pub fn register_components(ecs: &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Monster>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<Name>();
+    ecs.register::<Initiative>();
+    ecs.register::<MyTurn>();
+    ecs.register::<LastSeen>();
+    ecs.register::<LightSource>();
+    ecs.register::<Awareness>();
+    ecs.register::<Sneaking>();
+    ecs.register::<Tracker>();
+
+    ecs.register::<CombatStats>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<Ranged>();
+    ecs.register::<WantsToShoot>();
+    ecs.register::<Projectile>();
+    ecs.register::<ParticleLifetime>();
+    ecs.register::<Experience>();
+    ecs.register::<Item>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<Equippable>();
+    ecs.register::<Equipped>();
+    ecs.register::<MeleePowerBonus>();
+    ecs.register::<DefenseBonus>();
+    ecs.register::<WantsToEquip>();
+    ecs.register::<Consumable>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<StatusEffects>();
+    ecs.register::<InflictsStatus>();
+    ecs.register::<Hidden>();
+    ecs.register::<EntryTrigger>();
+    ecs.register::<EntityMoved>();
+    ecs.register::<ProvidesFood>();
+    ecs.register::<HungerClock>();
+    ecs.register::<Swimmer>();
+    ecs.register::<Breath>();
+    ecs.register::<Boat>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+    ecs.register::<SimpleMarker<StashMe>>();
+    ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+}
+
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
-    let context = RltkBuilder::simple80x50()
-        .with_title("My fancy RLTK game")
-        .build()?;
 
-    let mut gs = State {
-        ecs: World::new(),
-        runstate: RunState::Running,
+    // the options on the command line are remembered for the next time
+    let mut config = Config::load();
+    if config.apply_args(std::env::args().skip(1)) {
+        config.save();
+    }
+
+    let (tile_w, tile_h) = config.font.tile_size();
+    let font = config.font.file();
+    let mut builder = RltkBuilder::new()
+        .with_dimensions(80, 50)
+        .with_tile_dimensions(tile_w, tile_h)
+        .with_title("My fancy RLTK game")
+        .with_font(font, tile_w, tile_h)
+        .with_font(TILESET_FONT, SPRITE_SIZE, SPRITE_SIZE)
+        .with_simple_console(80, 50, TILESET_FONT);
+    // the glyphs go on a sparse console, so the sprites below show where nothing is drawn
+    builder = if config.background {
+        builder.with_sparse_console(80, 50, font)
+    } else {
+        builder.with_sparse_console_no_bg(80, 50, font)
     };
-    let map = map::Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
+    let mut context = builder.build()?;
+    if config.scanlines {
+        context.with_post_scanlines(config.screen_burn);
+    }
+
+    let mut gs = State { ecs: World::new() };
+    let mut rng = RandomNumberGenerator::new();
+    // the game starts on the islands, the dungeons are below
+    let (map, start, spawns) = overworld::build_overworld(&mut rng);
 
     // registro i componenti?
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<Name>();
+    register_components(&mut gs.ecs);
 
     // because many systems will require this
-    gs.ecs.insert(Point::new(player_x, player_y));
-
-    // creo un'entita player
-    gs.ecs
-        .create_entity()
-        .with(Position {
-            x: player_x,
-            y: player_y,
-        })
-        .with(Name {
-            name: "Player".to_string(),
-        })
-        .with(Renderable {
-            glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(BlocksTile {})
-        .with(Player {})
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 10,
-            dirty: true,
-        })
-        .build();
-
-    // creo un'entita monster per ogni stanza
-    let mut rng = RandomNumberGenerator::new();
-    for (i, room) in map.rooms.iter().skip(1).enumerate() {
-        let monster_pos = room.center();
-        let create_entity = gs.ecs.create_entity();
-        let glyph: rltk::FontCharType;
-        let roll = rng.roll_dice(1, 2);
-        let name: String;
-
-        match roll {
-            1 => {
-                glyph = to_cp437('$');
-                name = "Vosklamati".to_string();
-            }
-            _ => {
-                glyph = to_cp437('£');
-                name = "Vokastati".to_string();
-            }
-        }
-        create_entity
-            .with(Position {
-                x: monster_pos.0,
-                y: monster_pos.1,
-            })
-            .with(Name {
-                name: format!("{} #{}", &name, i),
-            })
-            .with(Monster {})
-            .with(BlocksTile {})
-            .with(Renderable {
-                glyph,
-                fg: RGB::named(rltk::GREEN),
-                bg: RGB::named(rltk::BLACK),
-            })
-            .with(Viewshed {
-                visible_tiles: Vec::new(),
-                range: 5,
-                dirty: true,
-            })
-            .build();
+    gs.ecs.insert(start);
+
+    let player_entity = spawner::player(&mut gs.ecs, start.x, start.y);
+    gs.ecs.insert(player_entity);
+    for (i, (x, y, marker)) in spawns.into_iter().enumerate() {
+        spawner::spawn_marker(&mut gs.ecs, x, y, marker, i, &mut rng);
     }
-    // for room in &map.rooms {
-    // }
 
     gs.ecs.insert(map);
+    gs.ecs.insert(rng);
+    gs.ecs.insert(RunState::Title);
+    gs.ecs.insert(RexAssets::load());
+    gs.ecs.insert(Overworld::default());
+    gs.ecs.insert(NoiseEvents::default());
+    gs.ecs.insert(PlayerRoom::default());
+    gs.ecs.insert(AutoPilot::default());
+    gs.ecs.insert(ScentOverlay::default());
+    gs.ecs.insert(ParticleBuilder::default());
+    gs.ecs.insert(GameLog {
+        entries: vec!["Welcome to Kerogueli".to_string()],
+    });
+    gs.ecs.insert(config);
+    gs.ecs.insert(KeyMap::load());
+    gs.ecs.insert(FogStyle::Tint(RGB::from_u8(30, 30, 80)));
     rltk::main_loop(context, gs)
 }
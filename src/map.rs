@@ -1,16 +1,71 @@
-use crate::Rect;
-use rltk::{Algorithm2D, BaseMap, Point, Rltk, RGB};
-use specs::World;
-use std::cmp::{max, min};
+use crate::corridors::{dig_corridors, CorridorStyle};
+use crate::room_theme::RoomTheme;
+use crate::{tile_sprite, Config, Rect, RenderMode, ASCII_CONSOLE, TILES_CONSOLE};
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
+use serde::{Deserialize, Serialize};
+use specs::{Entity, World};
+
+// the bottom of the screen is left for the GUI panel
+pub const MAPWIDTH: usize = 80;
+pub const MAPHEIGHT: usize = 43;
+pub const MAPCOUNT: usize = MAPWIDTH * MAPHEIGHT;
 
 // so I can copy and not "move", clone programmatically, and check for type equality
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Water,
     Ground,
+    Wall,
+    // the overworld
+    DeepWater,
+    Shallows,
+    Sand,
+    Ford,
+    Bridge,
+    DownStairs,
+    UpStairs,
 }
 
-#[derive(Default)]
+impl TileType {
+    /// Nothing walks through it
+    pub fn is_solid(&self) -> bool {
+        matches!(
+            self,
+            TileType::Water | TileType::Wall | TileType::DeepWater | TileType::Shallows
+        )
+    }
+
+    /// What the tile is called when the player looks at it
+    pub fn name(&self) -> &'static str {
+        match self {
+            TileType::Water => "water",
+            TileType::Ground => "ground",
+            TileType::Wall => "a wall",
+            TileType::DeepWater => "deep water",
+            TileType::Shallows => "shallow water",
+            TileType::Sand => "sand",
+            TileType::Ford => "a ford",
+            TileType::Bridge => "a bridge",
+            TileType::DownStairs => "stairs going down",
+            TileType::UpStairs => "stairs going up",
+        }
+    }
+
+    /// The water of the overworld and the pools of the dungeons, a swimmer can get in
+    pub fn is_swimmable(&self) -> bool {
+        matches!(
+            self,
+            TileType::Water | TileType::Shallows | TileType::DeepWater
+        )
+    }
+
+    /// Nothing can be seen through it, the water is open
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, TileType::Wall)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub rooms: Vec<Rect>,
@@ -19,8 +74,34 @@ pub struct Map {
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
+    pub light: Vec<RGB>,
+    #[serde(default)]
+    pub corridors: Vec<Vec<usize>>, // the tiles of each corridor, outside the rooms
+    #[serde(default)]
+    pub room_themes: Vec<RoomTheme>, // one for each room, missing means plain
+    #[serde(default)]
+    pub outdoors: bool, // lit by the sun, the overworld
+    #[serde(default)]
+    pub scent: Vec<i32>, // the trail of the player, fading with the turns
+
+    // a tile blocked by an entity and not only by the terrain, rebuilt every tick
+    #[serde(skip_serializing, skip_deserializing)]
+    pub occupied: Vec<bool>,
+
+    // what is on each tile, rebuilt every tick
+    #[serde(skip_serializing, skip_deserializing)]
+    pub tile_content: Vec<Vec<Entity>>,
 }
 
+/// Below this light level a tile is too dark to be seen from afar
+const LIT_THRESHOLD: f32 = 0.1;
+
+/// A step in the water costs a swimmer this much, as `SWIM_COST` is twice `MOVE_COST`
+const SWIM_STEP_COST: f32 = 2.;
+
+/// Light level of the tiles that are not reached by any light source
+const AMBIENT_LIGHT: f32 = 0.25;
+
 impl Map {
     /// Returns the index of a tile given its X and Y position
     pub const fn xy_idx(&self, x: i32, y: i32) -> usize {
@@ -35,140 +116,366 @@ impl Map {
             return false;
         }
         let idx = self.xy_idx(x, y);
-        !self.blocked[idx as usize]
+        !self.blocked[idx]
+    }
+
+    /// `true` if who can swim, or has a boat, can go into the tile: walkable or free water
+    pub fn can_swim_into(&self, idx: usize) -> bool {
+        !self.blocked[idx]
+            || (self.tiles[idx].is_swimmable() && !self.occupied.get(idx).copied().unwrap_or(false))
+    }
+
+    /// The tiles next to `idx` that can be moved into, through the water too if `swims`,
+    /// at a higher cost
+    pub fn exits(&self, idx: usize, swims: bool) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = rltk::SmallVec::new();
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
+
+        // Cardinal directions
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let (tx, ty) = (x + dx, y + dy);
+            if tx < 1 || tx > self.width - 1 || ty < 1 || ty > self.height - 1 {
+                continue;
+            }
+            let next = self.xy_idx(tx, ty);
+            let open = if swims {
+                self.can_swim_into(next)
+            } else {
+                self.is_exit_valid(tx, ty)
+            };
+            if !open {
+                continue;
+            }
+            let cost = if swims && self.tiles[next].is_swimmable() {
+                SWIM_STEP_COST
+            } else {
+                1.0
+            };
+            exits.push((next, cost));
+        }
+        exits
     }
 
     // == rooms and corridors ==
+    pub fn room_theme(&self, room: usize) -> RoomTheme {
+        self.room_themes
+            .get(room)
+            .copied()
+            .unwrap_or(RoomTheme::Plain)
+    }
+
     /// Build a room in a map
     fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Ground;
+        for tile in room.interior() {
+            let idx = self.xy_idx(tile.x, tile.y);
+            self.tiles[idx] = TileType::Ground;
+        }
+    }
+
+    /// Digs along the path, `width` tiles wide, and records it as a corridor
+    pub fn dig_path(&mut self, path: &[Point], width: i32) {
+        let mut corridor = Vec::new();
+        let mut previous: Option<Point> = None;
+        for step in path {
+            // moving is only orthogonal, so a diagonal step needs a corner
+            if let Some(prev) = previous {
+                if prev.x != step.x && prev.y != step.y {
+                    self.dig_square(step.x, prev.y, width, &mut corridor);
+                }
             }
+            self.dig_square(step.x, step.y, width, &mut corridor);
+            previous = Some(*step);
+        }
+        if !corridor.is_empty() {
+            self.corridors.push(corridor);
         }
     }
 
-    /// Build a horizontal tunnel between to rooms
-    fn apply_htunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            let idx_expanded = self.xy_idx(x, y + 1);
-            let height_times_width = self.width as usize * self.height as usize;
-            if idx > 0 && idx < height_times_width {
-                self.tiles[idx as usize] = TileType::Ground;
-                self.tiles[idx_expanded as usize] = TileType::Ground;
+    /// Turns into ground a square with the top left corner at `x`, `y`,
+    /// keeping the border of the map solid
+    fn dig_square(&mut self, x: i32, y: i32, width: i32, corridor: &mut Vec<usize>) {
+        for ty in y..y + width {
+            for tx in x..x + width {
+                if tx < 1 || tx > self.width - 2 || ty < 1 || ty > self.height - 2 {
+                    continue;
+                }
+                let idx = self.xy_idx(tx, ty);
+                let in_room = self.rooms.iter().any(|r| r.contains(Point::new(tx, ty)));
+                if !in_room && !corridor.contains(&idx) {
+                    corridor.push(idx);
+                }
+                self.tiles[idx] = TileType::Ground;
             }
         }
     }
-    fn apply_vtunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            let idx_expanded = self.xy_idx(x + 1, y);
-            let height_times_width = self.width as usize * self.height as usize;
-            if idx > 0 && idx < height_times_width {
-                self.tiles[idx as usize] = TileType::Ground;
-                self.tiles[idx_expanded as usize] = TileType::Ground;
+
+    /// Where to put something in the room: the centre, or the first tile of the floor
+    /// that isn't solid
+    pub fn room_spot(&self, room: &Rect) -> Option<Point> {
+        let centre = Point::from(room.center());
+        std::iter::once(centre)
+            .chain(room.interior())
+            .find(|p| !self.tiles[self.xy_idx(p.x, p.y)].is_solid())
+    }
+
+    /// How strong the scent of the player is on the tile
+    pub fn scent_at(&self, idx: usize) -> i32 {
+        self.scent.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Returns `true` if enough light reaches the tile to see it from afar
+    pub fn is_lit(&self, idx: usize) -> bool {
+        let light = self.light[idx];
+        light.r.max(light.g).max(light.b) > LIT_THRESHOLD
+    }
+
+    /// The tiles a projectile flies through from `from` towards `to`, at most `range` of them.
+    /// It stops at the first blocked tile, which is included unless it's opaque
+    pub fn projectile_path(&self, from: Point, to: Point, range: i32) -> Vec<Point> {
+        let mut path = Vec::new();
+        for tile in rltk::line2d(rltk::LineAlg::Bresenham, from, to) {
+            if tile == from {
+                continue;
+            }
+            if tile.x < 0 || tile.x >= self.width || tile.y < 0 || tile.y >= self.height {
+                break;
+            }
+            if path.len() as i32 >= range {
+                break;
+            }
+            let idx = self.xy_idx(tile.x, tile.y);
+            if self.is_opaque(idx) {
+                break;
+            }
+            path.push(tile);
+            if self.blocked[idx] {
+                break;
             }
         }
+        path
+    }
+
+    pub fn clear_content_index(&mut self) {
+        for content in self.tile_content.iter_mut() {
+            content.clear();
+        }
     }
 
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter().enumerate() {
-            self.blocked[i] = *tile == TileType::Water;
+            self.blocked[i] = tile.is_solid();
         }
+        self.occupied.clear();
+        self.occupied.resize(self.tiles.len(), false);
     }
 
-    /// Generate a new map with random rooms connected by corridors
-    pub fn new_map_rooms_and_corridors() -> Map {
-        let mut map = Map {
-            tiles: vec![TileType::Water; 80 * 50],
+    /// A map with nothing dug in it yet
+    pub fn new_blank() -> Map {
+        Map {
+            tiles: vec![TileType::Wall; MAPCOUNT],
             rooms: Vec::new(),
-            width: 80,
-            height: 50,
-            revealed_tiles: vec![false; 80 * 50],
-            visible_tiles: vec![false; 80 * 50],
-            blocked: vec![false; 80 * 50],
-        };
+            width: MAPWIDTH as i32,
+            height: MAPHEIGHT as i32,
+            revealed_tiles: vec![false; MAPCOUNT],
+            visible_tiles: vec![false; MAPCOUNT],
+            blocked: vec![false; MAPCOUNT],
+            light: vec![RGB::from_f32(0., 0., 0.); MAPCOUNT],
+            corridors: Vec::new(),
+            room_themes: Vec::new(),
+            outdoors: false,
+            scent: vec![0; MAPCOUNT],
+            occupied: vec![false; MAPCOUNT],
+            tile_content: vec![Vec::new(); MAPCOUNT],
+        }
+    }
+
+    /// Generate a new map with random rooms connected by corridors
+    pub fn new_map_rooms_and_corridors(
+        corridors: CorridorStyle,
+        rng: &mut RandomNumberGenerator,
+    ) -> Map {
+        let mut map = Map::new_blank();
+        map.dig_rooms_and_corridors(&[], corridors, rng);
+        map
+    }
 
+    /// Digs random rooms connected by corridors, the rooms keep out of the `reserved` areas
+    pub fn dig_rooms_and_corridors(
+        &mut self,
+        reserved: &[Rect],
+        corridors: CorridorStyle,
+        rng: &mut RandomNumberGenerator,
+    ) {
         const MAX_ROOMS: i32 = 26;
         const MIN_SIZE: i32 = 6;
         const MAX_SIZE: i32 = 15;
 
-        let mut rng = rltk::RandomNumberGenerator::new();
-
         for _ in 0..MAX_ROOMS {
             let w: i32 = rng.range(MIN_SIZE, MAX_SIZE);
             let h: i32 = rng.range(MIN_SIZE, MAX_SIZE);
 
-            let x = rng.roll_dice(1, 80 - w - 1);
-            let y = rng.roll_dice(1, 50 - h - 1);
+            let x = rng.roll_dice(1, self.width - w - 1);
+            let y = rng.roll_dice(1, self.height - h - 1);
             let new_room = Rect::new(x, y, w, h);
 
             // check if new room overlaps with others
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
+            let mut ok = !reserved.iter().any(|area| new_room.intersect(area));
+            for other_room in self.rooms.iter() {
                 if new_room.intersect(other_room) {
                     ok = false;
                 }
             }
             if ok {
-                map.apply_room_to_map(&new_room);
-
-                // add corridors if not the first room
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    if rng.range(0, 2) == 1 {
-                        map.apply_htunnel(prev_x, new_x, prev_y);
-                        map.apply_vtunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vtunnel(prev_y, new_y, prev_x);
-                        map.apply_htunnel(prev_x, new_x, new_y);
-                    }
-                }
-                map.rooms.push(new_room);
+                self.apply_room_to_map(&new_room);
+                self.rooms.push(new_room);
             }
         }
 
-        map
+        dig_corridors(self, corridors, rng);
+    }
+}
+
+/// How the tiles that were seen once, but are not in view anymore, get drawn
+#[derive(PartialEq, Copy, Clone)]
+pub enum FogStyle {
+    Greyscale,
+    Desaturate,
+    Dim(f32),  // multiply the colour, 0. is black
+    Tint(RGB), // shift the palette towards a colour
+}
+
+impl FogStyle {
+    pub fn apply(&self, colour: RGB) -> RGB {
+        match self {
+            FogStyle::Greyscale => colour.to_greyscale(),
+            FogStyle::Desaturate => colour.desaturate(),
+            FogStyle::Dim(amount) => colour * *amount,
+            FogStyle::Tint(tint) => colour.lerp(*tint, 0.5),
+        }
+    }
+
+    /// The next style in the list, to switch between them while playing
+    pub fn next(&self) -> FogStyle {
+        match self {
+            FogStyle::Greyscale => FogStyle::Desaturate,
+            FogStyle::Desaturate => FogStyle::Dim(0.5),
+            FogStyle::Dim(_) => FogStyle::Tint(RGB::from_u8(30, 30, 80)),
+            FogStyle::Tint(_) => FogStyle::Greyscale,
+        }
+    }
+}
+
+/// Returns glyph, foreground and background of a tile, fogged if it's not visible
+pub fn tile_glyph(idx: usize, map: &Map, fog: FogStyle) -> (rltk::FontCharType, RGB, RGB) {
+    let glyph;
+    let bg;
+    let fg;
+    match map.tiles[idx] {
+        TileType::Water => {
+            fg = RGB::from_u8(37, 150, 200);
+            bg = RGB::from_u8(37, 150, 190);
+            glyph = rltk::to_cp437('.');
+        }
+        TileType::Ground => {
+            fg = RGB::from_f32(0., 0., 0.);
+            bg = RGB::from_u8(234, 182, 118);
+            glyph = rltk::to_cp437('.');
+        }
+        TileType::Wall => {
+            fg = RGB::from_u8(150, 150, 150);
+            bg = RGB::from_u8(60, 60, 60);
+            glyph = rltk::to_cp437('#');
+        }
+        TileType::DeepWater => {
+            fg = RGB::from_u8(40, 90, 170);
+            bg = RGB::from_u8(20, 60, 140);
+            glyph = rltk::to_cp437('~');
+        }
+        TileType::Shallows => {
+            fg = RGB::from_u8(90, 190, 220);
+            bg = RGB::from_u8(37, 150, 190);
+            glyph = rltk::to_cp437('~');
+        }
+        TileType::Sand => {
+            fg = RGB::from_u8(200, 170, 110);
+            bg = RGB::from_u8(245, 225, 170);
+            glyph = rltk::to_cp437('.');
+        }
+        TileType::Ford => {
+            fg = RGB::from_u8(234, 182, 118);
+            bg = RGB::from_u8(80, 175, 200);
+            glyph = rltk::to_cp437('.');
+        }
+        TileType::Bridge => {
+            fg = RGB::from_u8(200, 150, 90);
+            bg = RGB::from_u8(110, 70, 35);
+            glyph = rltk::to_cp437('=');
+        }
+        TileType::DownStairs => {
+            fg = RGB::from_u8(255, 255, 255);
+            bg = RGB::from_u8(90, 60, 40);
+            glyph = rltk::to_cp437('>');
+        }
+        TileType::UpStairs => {
+            fg = RGB::from_u8(255, 255, 255);
+            bg = RGB::from_u8(234, 182, 118);
+            glyph = rltk::to_cp437('<');
+        }
+    }
+    (glyph, shade(idx, map, fog, fg), shade(idx, map, fog, bg))
+}
+
+/// Lights up a colour of the tile if it's in sight, or greys it with the fog if it's only remembered
+pub fn shade(idx: usize, map: &Map, fog: FogStyle, colour: RGB) -> RGB {
+    if map.visible_tiles[idx] {
+        // tint by the colour of the light, the darkvision keeps unlit tiles barely visible
+        let light = map.light[idx];
+        let light = RGB::from_f32(
+            light.r.max(AMBIENT_LIGHT),
+            light.g.max(AMBIENT_LIGHT),
+            light.b.max(AMBIENT_LIGHT),
+        );
+        colour * light
+    } else {
+        fog.apply(colour)
     }
 }
 
 pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
+    let fog = *ecs.fetch::<FogStyle>();
+    let mode = ecs.fetch::<Config>().render_mode;
     let mut x = 0;
     let mut y = 0;
 
-    for (idx, tile) in map.tiles.iter().enumerate() {
+    for idx in 0..map.tiles.len() {
         // render a tile based on its type
         if map.revealed_tiles[idx] {
-            let glyph;
-            let bg;
-            let fg;
-            match tile {
-                TileType::Water => {
-                    fg = RGB::from_u8(37, 150, 200);
-                    bg = RGB::from_u8(37, 150, 190);
-                    glyph = rltk::to_cp437('.');
+            match mode {
+                RenderMode::Ascii => {
+                    let (glyph, fg, bg) = tile_glyph(idx, &map, fog);
+                    ctx.set(x, y, fg, bg, glyph);
                 }
-                TileType::Ground => {
-                    fg = RGB::from_f32(0., 0., 0.);
-                    bg = RGB::from_u8(234, 182, 118);
-                    glyph = rltk::to_cp437('.');
+                RenderMode::Tiles => {
+                    // the sprite keeps its colours, the light and the fog tint it
+                    let tint = shade(idx, &map, fog, RGB::named(rltk::WHITE));
+                    ctx.set_active_console(TILES_CONSOLE);
+                    ctx.set(
+                        x,
+                        y,
+                        tint,
+                        RGB::named(rltk::BLACK),
+                        tile_sprite(map.tiles[idx]),
+                    );
+                    ctx.set_active_console(ASCII_CONSOLE);
                 }
             }
-            // this makes the revelaed tiles greyscale - not my fav effect
-            // if !map.visible_tiles[idx] {
-            //     bg = bg.to_greyscale();
-            // }
-
-            ctx.set(x, y, fg, bg, glyph);
         }
 
         // move the coords
         x += 1;
-        if x > 79 {
+        if x > map.width - 1 {
             x = 0;
             y += 1;
         }
@@ -177,7 +484,7 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] == TileType::Water
+        self.tiles[idx].is_opaque()
     }
 
     /// Get heuristic distance between two points using Pythagoras theorem
@@ -190,26 +497,7 @@ impl BaseMap for Map {
 
     /// Returns available exits, aka movements from the tile the entity is in
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
-        let mut exits = rltk::SmallVec::new();
-        let x = idx as i32 % self.width;
-        let y = idx as i32 / self.width;
-        let w = self.width as usize;
-
-        // Cardinal directions
-        if self.is_exit_valid(x - 1, y) {
-            exits.push((idx - 1, 1.0))
-        };
-        if self.is_exit_valid(x + 1, y) {
-            exits.push((idx + 1, 1.0))
-        };
-        if self.is_exit_valid(x, y - 1) {
-            exits.push((idx - w, 1.0))
-        };
-        if self.is_exit_valid(x, y + 1) {
-            exits.push((idx + w, 1.0))
-        };
-
-        exits
+        self.exits(idx, false)
     }
 }
 
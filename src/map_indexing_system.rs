@@ -7,17 +7,26 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        Entities<'a>,
     );
 
     // run every tick
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blocks_tile) = data;
+        let (mut map, position, blocks_tile, entities) = data;
         map.populate_blocked();
+        map.clear_content_index();
 
         // set to blocked on the map all the positions where there is an entity with component BlocksTile
         for (position, _blocks) in (&position, &blocks_tile).join() {
             let idx = map.xy_idx(position.x, position.y);
             map.blocked[idx] = true;
+            map.occupied[idx] = true;
+        }
+
+        // and index what is on each tile
+        for (entity, position) in (&entities, &position).join() {
+            let idx = map.xy_idx(position.x, position.y);
+            map.tile_content[idx].push(entity);
         }
     }
 }
@@ -0,0 +1,121 @@
+use super::{
+    CombatStats, DefenseBonus, Equipped, GameLog, InflictsStatus, MeleePowerBonus, Name,
+    ParticleBuilder, Position, StatusEffects, SufferDamage, WantsToMelee, HIT_PARTICLE_MS,
+};
+use rltk::{RandomNumberGenerator, RGB};
+use specs::prelude::*;
+
+pub struct MeleeCombatSystem {}
+
+impl<'a> System<'a> for MeleeCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, WantsToMelee>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Entity>, // the player
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Position>,
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut log,
+            player,
+            equipped,
+            power_bonuses,
+            defense_bonuses,
+            inflicts_status,
+            mut status,
+            mut rng,
+            positions,
+            mut particles,
+        ) = data;
+
+        for (ent, wants_melee, name, stats) in
+            (&entities, &wants_melee, &names, &combat_stats).join()
+        {
+            // dead things don't hit back
+            if stats.hp <= 0 {
+                continue;
+            }
+            let target_stats = match combat_stats.get(wants_melee.target) {
+                Some(target_stats) if target_stats.hp > 0 => target_stats,
+                _ => continue,
+            };
+            let target_name = names
+                .get(wants_melee.target)
+                .map_or("something", |n| n.name.as_str());
+
+            // what they wear counts too
+            let mut power_bonus = 0;
+            for (power, equipped_by) in (&power_bonuses, &equipped).join() {
+                if equipped_by.owner == ent {
+                    power_bonus += power.power;
+                }
+            }
+            let mut defense_bonus = 0;
+            for (defense, equipped_by) in (&defense_bonuses, &equipped).join() {
+                if equipped_by.owner == wants_melee.target {
+                    defense_bonus += defense.defense;
+                }
+            }
+
+            let damage = i32::max(
+                0,
+                (stats.power + power_bonus) - (target_stats.defense + defense_bonus),
+            );
+            if damage == 0 {
+                log.log(format!("{} is unable to hurt {}", name.name, target_name));
+            } else {
+                log.log(format!(
+                    "{} hits {}, for {} hp",
+                    name.name, target_name, damage
+                ));
+                SufferDamage::new_damage(
+                    &mut inflict_damage,
+                    wants_melee.target,
+                    damage,
+                    ent == *player,
+                );
+                if let Some(pos) = positions.get(wants_melee.target) {
+                    particles.request(
+                        pos.x,
+                        pos.y,
+                        RGB::named(rltk::ORANGE),
+                        rltk::to_cp437('‼'),
+                        HIT_PARTICLE_MS,
+                    );
+                }
+
+                // venomous bites and the like
+                if let Some(inflicts) = inflicts_status.get(ent) {
+                    if rng.roll_dice(1, 100) <= inflicts.chance {
+                        StatusEffects::apply(&mut status, wants_melee.target, inflicts.effect);
+                        log.log(format!(
+                            "{} is {}",
+                            target_name,
+                            inflicts.effect.kind.name().to_lowercase()
+                        ));
+                    }
+                }
+            }
+        }
+
+        wants_melee.clear();
+    }
+}
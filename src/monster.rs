@@ -1,9 +1,16 @@
-use crate::Renderable;
+use crate::{
+    player::{random_direction, SWIM_COST},
+    Awareness, AwarenessState, Boat, EntityMoved, GameLog, InBackpack, Initiative, MyTurn, Ranged,
+    Renderable, StatusEffects, StatusKind, Swimmer, Tracker, WantsToMelee, WantsToShoot, TURN_COST,
+};
 
 use super::{Map, Monster, Name, Position, Viewshed};
-use rltk::{Point, RGB};
+use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, RGB};
 use specs::prelude::*;
 
+/// Shooters back off when the player gets closer than this
+const KEEP_DISTANCE: f32 = 3.;
+
 pub struct MonsterSystem {}
 
 impl<'a> System<'a> for MonsterSystem {
@@ -16,48 +23,299 @@ impl<'a> System<'a> for MonsterSystem {
         ReadStorage<'a, Name>,
         WriteStorage<'a, Viewshed>, // see the player in a range, can be set to dirty
         ReadExpect<'a, Point>,      // for pathfinding
+        ReadStorage<'a, MyTurn>,    // only monsters with enough energy act
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, Awareness>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToMelee>,
+        ReadExpect<'a, Entity>, // the player, to attack them
+        WriteExpect<'a, GameLog>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteStorage<'a, EntityMoved>, // for the traps
+        ReadStorage<'a, Ranged>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Tracker>,
+        ReadStorage<'a, Swimmer>,
+        ReadStorage<'a, Boat>,
+        ReadStorage<'a, InBackpack>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut positions, mut map, mut renderable, monster, name, mut viewshed, player_pos) =
-            data;
+        let (
+            mut positions,
+            mut map,
+            mut renderable,
+            monster,
+            name,
+            mut viewshed,
+            player_pos,
+            turns,
+            mut initiatives,
+            mut awareness,
+            entities,
+            mut wants_to_melee,
+            player,
+            mut log,
+            status,
+            mut rng,
+            mut moved,
+            ranged,
+            mut wants_to_shoot,
+            trackers,
+            swimmers,
+            boats,
+            backpacks,
+        ) = data;
+
+        // who carries a boat floats over the water
+        let afloat: Vec<Entity> = (&backpacks, &boats)
+            .join()
+            .map(|(pack, _boat)| pack.owner)
+            .collect();
 
-        for (viewshed, _monster, renderable, name, position) in (
+        for (ent, viewshed, _monster, renderable, name, position, _turn, initiative, aware) in (
+            &entities,
             &mut viewshed,
             &monster,
             &mut renderable,
             &name,
             &mut positions,
+            &turns,
+            &mut initiatives,
+            &mut awareness,
         )
             .join()
         {
-            let distance = rltk::DistanceAlg::Pythagoras
-                .distance2d(Point::new(position.x, position.y), *player_pos);
-            if distance < 1.5 {
-                println!("Attacco!");
-                return;
-            }
+            // whatever the monster does, it costs a full turn
+            initiative.energy -= TURN_COST;
+
+            let start = (position.x, position.y);
+            let swims = swimmers.contains(ent) || afloat.contains(&ent);
+            'turn: {
+                // confused monsters stumble around instead
+                if let Some(active) = status.get(ent) {
+                    if active.has(StatusKind::Confusion) {
+                        let (dx, dy) = random_direction(&mut rng);
+                        let (x, y) = (position.x + dx, position.y + dy);
+                        if x >= 0 && x < map.width && y >= 0 && y < map.height {
+                            let idx = map.xy_idx(x, y);
+                            if can_enter(&map, idx, swims) {
+                                move_to(&mut map, position, viewshed, idx);
+                            }
+                        }
+                        break 'turn;
+                    }
+                }
 
-            renderable.bg = RGB::named(rltk::BLACK);
+                let distance = rltk::DistanceAlg::Pythagoras
+                    .distance2d(Point::new(position.x, position.y), *player_pos);
+                // only the four sides, as everybody moves and bumps
+                let adjacent = rltk::DistanceAlg::Manhattan
+                    .distance2d(Point::new(position.x, position.y), *player_pos)
+                    == 1.;
 
-            // if not close enough but sees the player
-            if viewshed.visible_tiles.contains(&*player_pos) {
-                println!("Monster {} sees the player", name.name);
-                renderable.bg = RGB::named(rltk::RED);
+                if distance < 1.5 || viewshed.visible_tiles.contains(&*player_pos) {
+                    if aware.state != AwarenessState::Hunting {
+                        log.log(format!("{} notices you", name.name));
+                    }
+                    aware.state = AwarenessState::Hunting;
+                    aware.investigate = Some(*player_pos);
 
-                // chase the player
-                let path = rltk::a_star_search(
-                    map.xy_idx(position.x, position.y) as i32,
-                    map.xy_idx(player_pos.x, player_pos.y) as i32,
-                    &mut *map, // &map would be a reference to the smart pointer `WriteExpect<Map>`, and not to the object itself
-                );
+                    // shooters keep their distance, and fire when they have a clear shot
+                    if let Some(ranged) = ranged.get(ent) {
+                        if distance < KEEP_DISTANCE
+                            && step_away(&mut map, position, viewshed, *player_pos, swims)
+                        {
+                            break 'turn;
+                        }
+                        let from = Point::new(position.x, position.y);
+                        let path = map.projectile_path(from, *player_pos, ranged.range);
+                        if !adjacent && path.last() == Some(&*player_pos) {
+                            wants_to_shoot
+                                .insert(
+                                    ent,
+                                    WantsToShoot {
+                                        target: *player_pos,
+                                    },
+                                )
+                                .expect("Unable to insert shot");
+                            break 'turn;
+                        }
+                    }
 
-                if path.success && path.steps.len() > 1 {
-                    position.x = path.steps[1] as i32 % map.width;
-                    position.y = path.steps[1] as i32 / map.width;
-                    viewshed.dirty = true;
+                    if adjacent {
+                        wants_to_melee
+                            .insert(ent, WantsToMelee { target: *player })
+                            .expect("Unable to insert attack");
+                    } else {
+                        // chase the player
+                        step_towards(&mut map, position, viewshed, *player_pos, swims);
+                    }
+                } else if let Some(target) = aware.investigate {
+                    // lost sight of the player or heard something, go have a look
+                    aware.state = AwarenessState::Suspicious;
+                    let arrived = position.x == target.x && position.y == target.y;
+                    if arrived || !step_towards(&mut map, position, viewshed, target, swims) {
+                        // nothing there, or can't get there: the trackers sniff around, the others give up
+                        aware.investigate = None;
+                        if !trackers.contains(ent)
+                            || !follow_scent(&mut map, position, viewshed, swims)
+                        {
+                            aware.state = AwarenessState::Unaware;
+                        }
+                    }
+                } else if aware.state == AwarenessState::Suspicious && trackers.contains(ent) {
+                    // on the trail of the player
+                    if !follow_scent(&mut map, position, viewshed, swims) {
+                        aware.state = AwarenessState::Unaware;
+                    }
                 }
             }
+            if (position.x, position.y) != start {
+                moved
+                    .insert(ent, EntityMoved {})
+                    .expect("Unable to insert moved marker");
+                // swimming is slow, as for the player, unless the monster has a boat
+                let tile = map.tiles[map.xy_idx(position.x, position.y)];
+                if tile.is_swimmable() && !afloat.contains(&ent) {
+                    initiative.energy -= SWIM_COST - TURN_COST;
+                }
+            }
+
+            renderable.bg = match aware.state {
+                AwarenessState::Unaware => RGB::named(rltk::BLACK),
+                AwarenessState::Suspicious => RGB::named(rltk::DARK_GOLDENROD),
+                AwarenessState::Hunting => RGB::named(rltk::RED),
+            };
+        }
+    }
+}
+
+/// The map as seen by a swimmer: the water is open, unless someone is in it
+struct SwimmingMap<'a> {
+    map: &'a Map,
+}
+
+impl BaseMap for SwimmingMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.map.exits(idx, true)
+    }
+}
+
+impl Algorithm2D for SwimmingMap<'_> {
+    fn dimensions(&self) -> Point {
+        self.map.dimensions()
+    }
+}
+
+/// Moves one step along the shortest path to `target`, returns `false` if there is none
+fn step_towards(
+    map: &mut Map,
+    position: &mut Position,
+    viewshed: &mut Viewshed,
+    target: Point,
+    swims: bool,
+) -> bool {
+    let start_idx = map.xy_idx(position.x, position.y);
+    let target_idx = map.xy_idx(target.x, target.y);
+
+    // the target is often blocked by the player, but we still want a path up to them
+    let target_blocked = (map.blocked[target_idx], map.occupied[target_idx]);
+    map.blocked[target_idx] = false;
+    map.occupied[target_idx] = false;
+    let path = if swims {
+        rltk::a_star_search(start_idx, target_idx, &SwimmingMap { map })
+    } else {
+        rltk::a_star_search(start_idx, target_idx, &*map)
+    };
+    (map.blocked[target_idx], map.occupied[target_idx]) = target_blocked;
+
+    if path.success && path.steps.len() > 1 && can_enter(map, path.steps[1], swims) {
+        move_to(map, position, viewshed, path.steps[1]);
+        return true;
+    }
+    false
+}
+
+/// Moves to the free neighbour farthest from `threat`, returns `false` if none is farther
+fn step_away(
+    map: &mut Map,
+    position: &mut Position,
+    viewshed: &mut Viewshed,
+    threat: Point,
+    swims: bool,
+) -> bool {
+    let here = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(position.x, position.y), threat);
+    let best = map
+        .exits(map.xy_idx(position.x, position.y), swims)
+        .into_iter()
+        .map(|(idx, _cost)| {
+            let tile = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+            (idx, rltk::DistanceAlg::Pythagoras.distance2d(tile, threat))
+        })
+        .filter(|(_idx, distance)| *distance > here)
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    match best {
+        Some((idx, _distance)) => {
+            move_to(map, position, viewshed, idx);
+            true
         }
+        None => false,
     }
 }
+
+/// Moves to the neighbour where the scent of the player is the strongest, if it's fresher
+/// than here. Returns `false` if the trail is lost
+fn follow_scent(
+    map: &mut Map,
+    position: &mut Position,
+    viewshed: &mut Viewshed,
+    swims: bool,
+) -> bool {
+    let here = map.xy_idx(position.x, position.y);
+    let best = map
+        .exits(here, swims)
+        .into_iter()
+        .map(|(idx, _cost)| (idx, map.scent_at(idx)))
+        .filter(|(_idx, scent)| *scent > map.scent_at(here))
+        .max_by_key(|(_idx, scent)| *scent);
+
+    match best {
+        Some((idx, _scent)) => {
+            move_to(map, position, viewshed, idx);
+            true
+        }
+        None => false,
+    }
+}
+
+fn can_enter(map: &Map, idx: usize, swims: bool) -> bool {
+    if swims {
+        map.can_swim_into(idx)
+    } else {
+        !map.blocked[idx]
+    }
+}
+
+fn move_to(map: &mut Map, position: &mut Position, viewshed: &mut Viewshed, idx: usize) {
+    // so the other monsters don't walk into the same tile this tick
+    let start_idx = map.xy_idx(position.x, position.y);
+    map.blocked[start_idx] = map.tiles[start_idx].is_solid();
+    map.occupied[start_idx] = false;
+    map.blocked[idx] = true;
+    map.occupied[idx] = true;
+
+    position.x = idx as i32 % map.width;
+    position.y = idx as i32 / map.width;
+    viewshed.dirty = true;
+}
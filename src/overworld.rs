@@ -0,0 +1,182 @@
+use super::corridors::l_path;
+use super::{Map, TileType};
+use rltk::{FastNoise, FractalType, NoiseType, Point, RandomNumberGenerator};
+
+// the height of the noise where each kind of tile starts
+const SHELF_HEIGHT: f32 = -0.05;
+const BEACH_HEIGHT: f32 = 0.12;
+const LAND_HEIGHT: f32 = 0.2;
+
+/// Near the edges of the map the islands sink, so the world is surrounded by the ocean
+const EDGE_FALLOFF: i32 = 8;
+
+/// How many dungeons can be found on the islands, at most
+const DUNGEON_ENTRANCES: usize = 3;
+
+/// The islands in the middle of the ocean, linked by bridges and fords, with the
+/// entrances of the dungeons on some of them. Returns the map, where the player lands and
+/// the markers of what to spawn
+pub fn build_overworld(rng: &mut RandomNumberGenerator) -> (Map, Point, Vec<(i32, i32, char)>) {
+    let mut map = Map::new_blank();
+    map.outdoors = true;
+
+    let mut noise = FastNoise::seeded(rng.next_u64());
+    noise.set_noise_type(NoiseType::SimplexFractal);
+    noise.set_fractal_type(FractalType::FBM);
+    noise.set_fractal_octaves(4);
+    noise.set_fractal_gain(0.5);
+    noise.set_fractal_lacunarity(2.0);
+    noise.set_frequency(0.06);
+
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let edge = x.min(y).min(map.width - 1 - x).min(map.height - 1 - y);
+            let mut height = noise.get_noise(x as f32, y as f32);
+            if edge < EDGE_FALLOFF {
+                height -= (EDGE_FALLOFF - edge) as f32 / EDGE_FALLOFF as f32 * 0.5;
+            }
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = if edge == 0 || height < SHELF_HEIGHT {
+                TileType::DeepWater
+            } else if height < BEACH_HEIGHT {
+                TileType::Shallows
+            } else if height < LAND_HEIGHT {
+                TileType::Sand
+            } else {
+                TileType::Ground
+            };
+        }
+    }
+
+    // the noise may leave the sea empty, a single island in the middle then
+    let mut islands = find_islands(&map);
+    if islands.is_empty() {
+        let centre = Point::new(map.width / 2, map.height / 2);
+        for y in centre.y - 3..=centre.y + 3 {
+            for x in centre.x - 6..=centre.x + 6 {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Ground;
+            }
+        }
+        islands = find_islands(&map);
+    }
+    islands.sort_by_key(|island| std::cmp::Reverse(island.len()));
+
+    link_islands(&mut map, &islands, rng);
+
+    // the dungeons are dug in the biggest islands, away from the beach
+    for island in islands.iter().take(DUNGEON_ENTRANCES) {
+        let inland = island
+            .iter()
+            .filter(|&&idx| map.tiles[idx] == TileType::Ground)
+            .copied()
+            .collect::<Vec<_>>();
+        if let Some(&idx) = rng.random_slice_entry(&inland) {
+            map.tiles[idx] = TileType::DownStairs;
+        }
+    }
+
+    // the player lands somewhere on the biggest island
+    let landing = islands[0]
+        .iter()
+        .filter(|&&idx| map.tiles[idx] != TileType::DownStairs)
+        .copied()
+        .collect::<Vec<_>>();
+    let idx = *rng.random_slice_entry(&landing).unwrap_or(&islands[0][0]);
+    let start = Point::new(idx as i32 % map.width, idx as i32 / map.width);
+
+    // and a boat is waiting on one of its beaches
+    let mut spawns = Vec::new();
+    let shore = islands[0]
+        .iter()
+        .filter(|&&tile| map.tiles[tile] == TileType::Sand && tile != idx)
+        .copied()
+        .collect::<Vec<_>>();
+    if let Some(&tile) = rng.random_slice_entry(&shore) {
+        spawns.push((tile as i32 % map.width, tile as i32 / map.width, 'B'));
+    }
+    (map, start, spawns)
+}
+
+/// The tiles of each island, walking only orthogonally over the land
+fn find_islands(map: &Map) -> Vec<Vec<usize>> {
+    let is_land = |idx: usize| matches!(map.tiles[idx], TileType::Ground | TileType::Sand);
+    let mut seen = vec![false; map.tiles.len()];
+    let mut islands = Vec::new();
+    for start in 0..map.tiles.len() {
+        if seen[start] || !is_land(start) {
+            continue;
+        }
+        let mut island = Vec::new();
+        let mut open = vec![start];
+        seen[start] = true;
+        while let Some(idx) = open.pop() {
+            island.push(idx);
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if nx < 0 || nx >= map.width || ny < 0 || ny >= map.height {
+                    continue;
+                }
+                let next = map.xy_idx(nx, ny);
+                if !seen[next] && is_land(next) {
+                    seen[next] = true;
+                    open.push(next);
+                }
+            }
+        }
+        islands.push(island);
+    }
+    islands
+}
+
+/// Joins every island to the closest one already reached from the first, wading
+/// through the shallows and with a bridge over the deep water
+fn link_islands(map: &mut Map, islands: &[Vec<usize>], rng: &mut RandomNumberGenerator) {
+    // only the coast matters to find the shortest crossing, an island without a beach is all coast
+    let point = |idx: usize| Point::new(idx as i32 % map.width, idx as i32 / map.width);
+    let coasts = islands
+        .iter()
+        .map(|island| {
+            let beach = island
+                .iter()
+                .filter(|&&idx| map.tiles[idx] == TileType::Sand)
+                .map(|&idx| point(idx))
+                .collect::<Vec<_>>();
+            if beach.is_empty() {
+                island.iter().map(|&idx| point(idx)).collect()
+            } else {
+                beach
+            }
+        })
+        .collect::<Vec<Vec<Point>>>();
+
+    let mut reached = vec![false; islands.len()];
+    reached[0] = true;
+    for _ in 1..islands.len() {
+        let mut best: Option<(f32, Point, Point, usize)> = None;
+        for (from, _) in reached.iter().enumerate().filter(|(_, r)| **r) {
+            for (to, _) in reached.iter().enumerate().filter(|(_, r)| !**r) {
+                for a in coasts[from].iter() {
+                    for b in coasts[to].iter() {
+                        let distance = rltk::DistanceAlg::Manhattan.distance2d(*a, *b);
+                        if best.is_none_or(|(d, ..)| distance < d) {
+                            best = Some((distance, *a, *b, to));
+                        }
+                    }
+                }
+            }
+        }
+        let Some((_, a, b, to)) = best else {
+            break;
+        };
+        reached[to] = true;
+        for step in l_path(a, b, rng.roll_dice(1, 2) == 1) {
+            let idx = map.xy_idx(step.x, step.y);
+            map.tiles[idx] = match map.tiles[idx] {
+                TileType::Shallows => TileType::Ford,
+                TileType::DeepWater => TileType::Bridge,
+                tile => tile,
+            };
+        }
+    }
+}
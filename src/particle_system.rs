@@ -0,0 +1,147 @@
+use super::{Map, ParticleLifetime, Position, Projectile, Renderable};
+use rltk::{FontCharType, RGB};
+use specs::prelude::*;
+
+/// How long the projectile stays on each tile of its flight
+const MS_PER_STEP: f32 = 30.;
+
+/// Drawn above everything else
+pub const PARTICLE_RENDER_ORDER: i32 = -1;
+
+/// How long the flash of a hit lasts
+pub const HIT_PARTICLE_MS: f32 = 200.;
+const TELEPORT_PARTICLE_MS: f32 = 400.;
+
+struct ParticleRequest {
+    x: i32,
+    y: i32,
+    fg: RGB,
+    bg: RGB,
+    glyph: FontCharType,
+    lifetime: f32,
+}
+
+/// The particles asked for this tick, created all together by the `ParticleSpawnSystem`
+#[derive(Default)]
+pub struct ParticleBuilder {
+    requests: Vec<ParticleRequest>,
+}
+
+impl ParticleBuilder {
+    pub fn request(&mut self, x: i32, y: i32, fg: RGB, glyph: FontCharType, lifetime: f32) {
+        self.requests.push(ParticleRequest {
+            x,
+            y,
+            fg,
+            bg: RGB::named(rltk::BLACK),
+            glyph,
+            lifetime,
+        });
+    }
+
+    /// Where something vanishes, and where it appears again
+    pub fn teleport(&mut self, x: i32, y: i32) {
+        self.burst(
+            x,
+            y,
+            RGB::named(rltk::LIGHT_BLUE),
+            rltk::to_cp437('░'),
+            TELEPORT_PARTICLE_MS,
+        );
+    }
+
+    /// A ring of particles around a point, for the big moments
+    pub fn burst(&mut self, x: i32, y: i32, fg: RGB, glyph: FontCharType, lifetime: f32) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx != 0 || dy != 0 {
+                    self.request(x + dx, y + dy, fg, glyph, lifetime);
+                }
+            }
+        }
+    }
+}
+
+pub struct ParticleSpawnSystem {}
+
+impl<'a> System<'a> for ParticleSpawnSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, ParticleLifetime>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadExpect<'a, Map>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut positions, mut renderables, mut particles, mut builder, map) = data;
+        for new in builder.requests.drain(..) {
+            // a burst at the edge of the map spills over it
+            if new.x < 0 || new.x >= map.width || new.y < 0 || new.y >= map.height {
+                continue;
+            }
+            let p = entities.create();
+            positions
+                .insert(p, Position { x: new.x, y: new.y })
+                .expect("Unable to insert position");
+            renderables
+                .insert(
+                    p,
+                    Renderable {
+                        glyph: new.glyph,
+                        fg: new.fg,
+                        bg: new.bg,
+                        render_order: PARTICLE_RENDER_ORDER,
+                        sprite: None,
+                    },
+                )
+                .expect("Unable to insert renderable");
+            particles
+                .insert(
+                    p,
+                    ParticleLifetime {
+                        lifetime_ms: new.lifetime,
+                    },
+                )
+                .expect("Unable to insert lifetime");
+        }
+    }
+}
+
+/// Ages the particles and moves the projectiles along their flight.
+/// Called every frame, even while the game waits for the player
+pub fn update_particles(ecs: &mut World, frame_time_ms: f32) {
+    let mut finished: Vec<Entity> = Vec::new();
+    {
+        let entities = ecs.entities();
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        for (ent, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= frame_time_ms;
+            if particle.lifetime_ms < 0. {
+                finished.push(ent);
+            }
+        }
+
+        let mut projectiles = ecs.write_storage::<Projectile>();
+        let mut positions = ecs.write_storage::<Position>();
+        for (ent, projectile, pos) in (&entities, &mut projectiles, &mut positions).join() {
+            projectile.timer += frame_time_ms;
+            if projectile.timer < MS_PER_STEP {
+                continue;
+            }
+            projectile.timer = 0.;
+            projectile.step += 1;
+            match projectile.path.get(projectile.step) {
+                Some(tile) => {
+                    pos.x = tile.x;
+                    pos.y = tile.y;
+                }
+                None => finished.push(ent),
+            }
+        }
+    }
+    ecs.delete_entities(&finished)
+        .expect("Unable to delete particles");
+}
@@ -1,52 +1,294 @@
-use crate::{Map, Viewshed};
+use crate::{
+    Boat, CombatStats, Consumable, EntityMoved, GameLog, InBackpack, Initiative, Item, Map, MyTurn,
+    NoiseEvents, ParticleBuilder, Sneaking, StatusEffects, StatusKind, Swimmer, Viewshed,
+    WantsToEquip, WantsToMelee, WantsToPickupItem, WantsToShoot, WantsToUseItem, TURN_COST,
+};
 
 use super::{Player, Position};
-use rltk::Point;
+use rltk::{Point, RandomNumberGenerator};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+// energy spent by each player action, compared against TURN_COST
+pub const MOVE_COST: i32 = 100;
+pub const ATTACK_COST: i32 = 120;
+pub const WAIT_COST: i32 = TURN_COST; // one whole turn
+pub const REST_COST: i32 = TURN_COST; // a full turn for each step of resting, so the healing follows the turns
+pub const TELEPORT_COST: i32 = 200;
+pub const PICKUP_COST: i32 = 100;
+pub const EQUIP_COST: i32 = 100;
+pub const USE_COST: i32 = 100;
+pub const SHOOT_COST: i32 = 100;
+pub const STAIRS_COST: i32 = 100;
+pub const SWIM_COST: i32 = 200;
+
+// how far monsters can hear each player action
+pub const MOVE_NOISE: f32 = 6.;
+pub const ATTACK_NOISE: f32 = 10.;
+pub const TELEPORT_NOISE: f32 = 8.;
+pub const SHOOT_NOISE: f32 = 4.;
+
+/// Sneaking muffles every noise the player makes
+const SNEAK_NOISE_FACTOR: f32 = 0.3;
+
+/// Moves the player, or attacks whatever is standing in the way.
+/// Returns the energy spent, 0 if nothing happened
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> i32 {
     // this gains access to players and positions in the world (ecs)
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viesheds = ecs.write_storage::<Viewshed>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let mut moved = ecs.write_storage::<EntityMoved>();
+    let entities = ecs.entities();
+    let player_entity = ecs.fetch::<Entity>();
     let mut ppos = ecs.write_resource::<Point>();
+    let mut noises = ecs.write_resource::<NoiseEvents>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let noise_factor = noise_factor((&players, &sneaking).join().next().is_some());
 
     let map = ecs.fetch::<Map>();
 
-    for (_player, pos, viewshed) in (&mut players, &mut positions, &mut viesheds).join() {
-        let new_x: i32 = pos.x + delta_x;
-        let new_y: i32 = pos.y + delta_y;
+    // confused players stumble around
+    let status = ecs.read_storage::<StatusEffects>();
+    let (delta_x, delta_y) = match status.get(*player_entity) {
+        Some(active) if active.has(StatusKind::Confusion) => {
+            random_direction(&mut ecs.write_resource::<RandomNumberGenerator>())
+        }
+        _ => (delta_x, delta_y),
+    };
+
+    let new_x: i32 = (ppos.x + delta_x).clamp(0, map.width - 1);
+    let new_y: i32 = (ppos.y + delta_y).clamp(0, map.height - 1);
 
-        if !map.blocked[map.xy_idx(new_x, new_y)] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+    // bumping into something that can fight is an attack
+    for (target, _stats, pos) in (&entities, &combat_stats, &positions).join() {
+        if pos.x == new_x && pos.y == new_y && target != *player_entity {
+            wants_to_melee
+                .insert(*player_entity, WantsToMelee { target })
+                .expect("Add target failed");
+            noises.make(new_x, new_y, ATTACK_NOISE * noise_factor);
+            return ATTACK_COST;
+        }
+    }
+
+    // the water is in the way only of who can't swim and has no boat
+    let idx = map.xy_idx(new_x, new_y);
+    let afloat = (
+        &ecs.read_storage::<InBackpack>(),
+        &ecs.read_storage::<Boat>(),
+    )
+        .join()
+        .any(|(pack, _boat)| pack.owner == *player_entity);
+    let swimmer = ecs.read_storage::<Swimmer>().contains(*player_entity);
+    let into_water = map.tiles[idx].is_swimmable() && (afloat || swimmer);
+    let from_land = !map.tiles[map.xy_idx(ppos.x, ppos.y)].is_swimmable();
+
+    let mut cost = 0;
+    for (_player, pos, viewshed) in (&mut players, &mut positions, &mut viesheds).join() {
+        let open = if afloat || swimmer {
+            map.can_swim_into(idx)
+        } else {
+            !map.blocked[idx]
+        };
+        if open {
+            pos.x = new_x;
+            pos.y = new_y;
             viewshed.dirty = true;
 
             // update the point on the ecs
             ppos.x = pos.x;
             ppos.y = pos.y;
+            noises.make(pos.x, pos.y, MOVE_NOISE * noise_factor);
+            moved
+                .insert(*player_entity, EntityMoved {})
+                .expect("Unable to insert moved marker");
+            cost = MOVE_COST;
+            if into_water && !afloat {
+                cost = SWIM_COST;
+            }
+            if into_water && from_land {
+                ecs.write_resource::<GameLog>().log(if afloat {
+                    "You push your boat into the water"
+                } else {
+                    "You start swimming"
+                });
+            }
         }
     }
+    cost
 }
 
-pub fn move_to_random_room(ecs: &mut World) {
+pub fn move_to_random_room(ecs: &mut World) -> i32 {
     // prendo in read mode le stanze
     let map = ecs.fetch::<Map>();
+    if map.rooms.is_empty() {
+        ecs.write_resource::<GameLog>()
+            .log("Nothing happens out here");
+        return 0;
+    }
     let mut rng = rltk::RandomNumberGenerator::new();
     let room_index = rng.range(0, map.rooms.len());
+    let Some(spot) = map.room_spot(&map.rooms[room_index]) else {
+        return 0;
+    };
 
     // prendo tutti i componenti Position e Player
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut ppos = ecs.write_resource::<Point>();
+    let mut noises = ecs.write_resource::<NoiseEvents>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let noise_factor = noise_factor((&players, &sneaking).join().next().is_some());
+    let mut log = ecs.write_resource::<GameLog>();
+    let mut moved = ecs.write_storage::<EntityMoved>();
+    let entities = ecs.entities();
 
     // joino Position e Player, quindi difatto prendo
     // l'unica entita che ha entrambi, aka il player
-    for (_player, pos, viewshed) in (&mut players, &mut positions, &mut viewsheds).join() {
-        pos.x = map.rooms[room_index].center().0;
-        pos.y = map.rooms[room_index].center().1;
+    let mut particles = ecs.write_resource::<ParticleBuilder>();
+    for (ent, _player, pos, viewshed) in
+        (&entities, &mut players, &mut positions, &mut viewsheds).join()
+    {
+        particles.teleport(pos.x, pos.y);
+        pos.x = spot.x;
+        pos.y = spot.y;
+        ppos.x = pos.x;
+        ppos.y = pos.y;
         viewshed.dirty = true;
-        println!("Player teleported")
+        noises.make(pos.x, pos.y, TELEPORT_NOISE * noise_factor);
+        moved
+            .insert(ent, EntityMoved {})
+            .expect("Unable to insert moved marker");
+        particles.teleport(pos.x, pos.y);
+        log.log("You are teleported");
+    }
+    TELEPORT_COST
+}
+
+/// Picks up an item from the floor
+pub fn get_item(ecs: &mut World) -> i32 {
+    let player_pos = ecs.fetch::<Point>();
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    let target_item = (&entities, &items, &positions)
+        .join()
+        .find(|(_item, _i, pos)| pos.x == player_pos.x && pos.y == player_pos.y)
+        .map(|(item, _i, _pos)| item);
+
+    match target_item {
+        None => {
+            log.log("There is nothing here to pick up");
+            0
+        }
+        Some(item) => {
+            let mut pickup = ecs.write_storage::<WantsToPickupItem>();
+            pickup
+                .insert(
+                    *player_entity,
+                    WantsToPickupItem {
+                        collected_by: *player_entity,
+                        item,
+                    },
+                )
+                .expect("Unable to insert want to pickup");
+            PICKUP_COST
+        }
+    }
+}
+
+/// Uses a consumable, or wears anything else
+pub fn use_item(ecs: &mut World, item: Entity) -> i32 {
+    if ecs.read_storage::<Consumable>().get(item).is_none() {
+        return equip_item(ecs, item);
+    }
+
+    let player_entity = ecs.fetch::<Entity>();
+    let mut wants_use = ecs.write_storage::<WantsToUseItem>();
+    wants_use
+        .insert(*player_entity, WantsToUseItem { item })
+        .expect("Unable to insert want to use");
+    USE_COST
+}
+
+/// Wears the item, or takes it off if it's already worn
+pub fn equip_item(ecs: &mut World, item: Entity) -> i32 {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut wants_equip = ecs.write_storage::<WantsToEquip>();
+    wants_equip
+        .insert(*player_entity, WantsToEquip { item })
+        .expect("Unable to insert want to equip");
+    EQUIP_COST
+}
+
+/// Shoots with the worn ranged weapon towards `target`
+pub fn shoot(ecs: &mut World, target: Point) -> i32 {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let mut wants_shoot = ecs.write_storage::<WantsToShoot>();
+    let mut noises = ecs.write_resource::<NoiseEvents>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let noise_factor = noise_factor(sneaking.get(*player_entity).is_some());
+
+    wants_shoot
+        .insert(*player_entity, WantsToShoot { target })
+        .expect("Unable to insert want to shoot");
+    noises.make(player_pos.x, player_pos.y, SHOOT_NOISE * noise_factor);
+    SHOOT_COST
+}
+
+/// Pays the energy for the action the player just took, ending their turn
+pub fn spend_energy(ecs: &mut World, cost: i32) {
+    let players = ecs.read_storage::<Player>();
+    let mut initiatives = ecs.write_storage::<Initiative>();
+    let mut turns = ecs.write_storage::<MyTurn>();
+    let entities = ecs.entities();
+
+    for (ent, _player, initiative) in (&entities, &players, &mut initiatives).join() {
+        initiative.energy -= cost;
+        turns.remove(ent);
+    }
+}
+
+/// Starts or stops sneaking, it doesn't take a turn
+pub fn toggle_sneak(ecs: &mut World) {
+    let players = ecs.read_storage::<Player>();
+    let mut sneaking = ecs.write_storage::<Sneaking>();
+    let entities = ecs.entities();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    for (ent, _player) in (&entities, &players).join() {
+        if sneaking.remove(ent).is_some() {
+            log.log("You stop sneaking");
+        } else {
+            sneaking
+                .insert(ent, Sneaking {})
+                .expect("Unable to insert sneaking");
+            log.log("You start sneaking");
+        }
+    }
+}
+
+/// How much of the noise of an action is actually made
+fn noise_factor(sneaking: bool) -> f32 {
+    if sneaking {
+        SNEAK_NOISE_FACTOR
+    } else {
+        1.
+    }
+}
+
+/// One of the four directions, at random
+pub fn random_direction(rng: &mut RandomNumberGenerator) -> (i32, i32) {
+    match rng.roll_dice(1, 4) {
+        1 => (0, -1),
+        2 => (0, 1),
+        3 => (-1, 0),
+        _ => (1, 0),
     }
 }
@@ -0,0 +1,249 @@
+use super::{Map, Rect};
+use crate::corridors::CorridorStyle;
+use crate::room_theme::{theme_rooms, RoomTheme};
+use crate::vault::Vault;
+use rltk::{Point, RandomNumberGenerator};
+
+// the glyphs: `.` ground, `~` water, `#` wall, `@` where the player starts,
+// `g` a monster, `!` a potion, `%` a ration, `^` a trap, `[` armour, `)` a bow.
+// A space leaves the map as it is
+
+/// One level in ten is made by hand
+const LEVEL_CHANCE: i32 = 10;
+/// Half of the levels get a section on the edge
+const SECTION_CHANCE: i32 = 2;
+/// A room in four gets furnished
+const ROOM_CHANCE: i32 = 4;
+/// A level made by hand is cut in areas this big, they are its rooms
+const AREA_WIDTH: i32 = 8;
+const AREA_HEIGHT: i32 = 6;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum HAnchor {
+    Left,
+    Centre,
+    Right,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VAnchor {
+    Top,
+    Centre,
+    Bottom,
+}
+
+/// A section stuck to an edge of the map, rooms and corridors are dug around it
+pub struct Section {
+    pub template: &'static str,
+    pub h: HAnchor,
+    pub v: VAnchor,
+}
+
+const DROWNED_TEMPLE: &str = "
+################################################
+#......................##......................#
+#.~~~~~~.......g.......##.......g.......~~~~~~.#
+#.~~~~~~...............##...............~~~~~~.#
+#.~~~~~~.......................................#
+#..........#####################...............#
+#..........#.........!.........#...........g...#
+#....g.....#.~~~~~~~.%.~~~~~~~.#...............#
+#..........#.~~~~~~~...~~~~~~~.#...............#
+#..........#.......g...........#.......~~~.....#
+#..........######.......########.......~~~.....#
+#..................@...........................#
+#....~~~~......................................#
+#....~~~~.........#######...######......g......#
+#.................#..^.........[.#.............#
+#.......g.........#..g.....).....#.............#
+#.................################.............#
+#..~~~~~~..............................~~~~~~..#
+#..~~~~~~...........g..................~~~~~~..#
+#..............................................#
+################################################
+";
+
+const LEVELS: &[&str] = &[DROWNED_TEMPLE];
+
+const GUARD_POST: Section = Section {
+    template: "
+  #######
+  #.....#
+  #.g.g.#
+....!...#
+  #.....#
+  #######
+",
+    h: HAnchor::Right,
+    v: VAnchor::Centre,
+};
+
+const SHORE: Section = Section {
+    template: "
+...........................
+~~~.....~~~~~~~.....~~~~~~~
+~~~~~..%...~~~...!..~~~~~~~
+~~~~~~~~~~~~~~~~~~~~~~~~~~~
+",
+    h: HAnchor::Centre,
+    v: VAnchor::Bottom,
+};
+
+const RUINS: Section = Section {
+    template: "
+#####.####
+#..)....^.
+#.####.#..
+#.#..g.#.
+#...%..#
+####.###
+",
+    h: HAnchor::Left,
+    v: VAnchor::Top,
+};
+
+const SECTIONS: &[Section] = &[GUARD_POST, SHORE, RUINS];
+
+const PILLARS: &str = "
+.....
+.#.#.
+..!..
+.#.#.
+.....
+";
+
+const POOL: &str = "
+.~~~~~.
+.~~~~~.
+...g...
+.~~~~~.
+";
+
+const DEN: &str = "
+#....#
+..gg..
+#.%..#
+";
+
+const ROOMS: &[&str] = &[PILLARS, POOL, DEN];
+
+/// Makes the map for a new level, sometimes by hand, sometimes with some prefabs in it.
+/// The rooms are joined by `corridors`.
+/// Returns the map, where the player starts and the spawn markers of the prefabs
+pub fn build_level(
+    rng: &mut RandomNumberGenerator,
+    corridors: CorridorStyle,
+) -> (Map, Point, Vec<(i32, i32, char)>) {
+    if rng.roll_dice(1, LEVEL_CHANCE) == 1 {
+        return whole_level(&Vault::from_text(LEVELS[rng.range(0, LEVELS.len())]));
+    }
+
+    let mut map = Map::new_blank();
+    let mut spawns = Vec::new();
+
+    // the section goes in first, so the rooms keep away from it
+    let mut section = None;
+    if rng.roll_dice(1, SECTION_CHANCE) == 1 {
+        let template = &SECTIONS[rng.range(0, SECTIONS.len())];
+        let vault = Vault::from_text(template.template);
+        let (x, y) = anchored_position(&map, &vault, template.h, template.v);
+        spawns.extend(vault.stamp(&mut map, x, y));
+        section = Some((vault, x, y));
+    }
+    let reserved: Vec<Rect> = section
+        .iter()
+        .map(|(vault, x, y)| Rect::new(x - 1, y - 1, vault.width + 1, vault.height + 1))
+        .collect();
+    map.dig_rooms_and_corridors(&reserved, corridors, rng);
+    if let Some((vault, x, y)) = section {
+        vault.connect(&mut map, x, y);
+    }
+
+    theme_rooms(&mut map, rng);
+    spawns.extend(furnish_rooms(&mut map, rng));
+    let start = Point::from(map.rooms[0].center());
+    (map, start, spawns)
+}
+
+/// The template is the whole level, in the middle of the map. `@` is where the player starts
+fn whole_level(vault: &Vault) -> (Map, Point, Vec<(i32, i32, char)>) {
+    let mut map = Map::new_blank();
+    let x = (map.width - vault.width).max(0) / 2;
+    let y = (map.height - vault.height).max(0) / 2;
+    let (start, spawns): (Vec<_>, Vec<_>) = vault
+        .stamp(&mut map, x, y)
+        .into_iter()
+        .partition(|(_x, _y, marker)| *marker == '@');
+
+    let start = start.first().map_or(
+        Point::new(map.width / 2, map.height / 2),
+        |(x, y, _marker)| Point::new(*x, *y),
+    );
+
+    // the areas with enough room to walk are the rooms, to spawn and teleport in
+    for ay in (y..y + vault.height).step_by(AREA_HEIGHT as usize) {
+        for ax in (x..x + vault.width).step_by(AREA_WIDTH as usize) {
+            let width = AREA_WIDTH.min(x + vault.width - ax);
+            let height = AREA_HEIGHT.min(y + vault.height - ay);
+            let area = Rect::new(ax - 1, ay - 1, width, height);
+            let open = area
+                .interior()
+                .filter(|p| !map.tiles[map.xy_idx(p.x, p.y)].is_solid())
+                .count() as i32;
+            if open * 3 >= area.area() {
+                map.rooms.push(area);
+            }
+        }
+    }
+
+    // the player starts in the first room
+    match map.rooms.iter().position(|room| room.contains(start)) {
+        Some(i) => map.rooms.swap(0, i),
+        None => map
+            .rooms
+            .insert(0, Rect::new(start.x - 1, start.y - 1, 1, 1)),
+    }
+    (map, start, spawns)
+}
+
+fn anchored_position(map: &Map, vault: &Vault, h: HAnchor, v: VAnchor) -> (i32, i32) {
+    // the outer border of the map stays as it is
+    let x = match h {
+        HAnchor::Left => 1,
+        HAnchor::Centre => (map.width - vault.width) / 2,
+        HAnchor::Right => map.width - vault.width - 1,
+    };
+    let y = match v {
+        VAnchor::Top => 1,
+        VAnchor::Centre => (map.height - vault.height) / 2,
+        VAnchor::Bottom => map.height - vault.height - 1,
+    };
+    (x, y)
+}
+
+/// Swaps some of the rooms with a prefab that fits in them, the first room is left alone
+fn furnish_rooms(map: &mut Map, rng: &mut RandomNumberGenerator) -> Vec<(i32, i32, char)> {
+    let templates: Vec<Vault> = ROOMS.iter().map(|t| Vault::from_text(t)).collect();
+    let mut spawns = Vec::new();
+    let rooms = map.rooms.clone();
+    for (i, room) in rooms.iter().enumerate().skip(1) {
+        // the themed rooms are already decorated
+        if map.room_theme(i) != RoomTheme::Plain || rng.roll_dice(1, ROOM_CHANCE) > 1 {
+            continue;
+        }
+        let (width, height) = (room.width(), room.height());
+        let fitting: Vec<&Vault> = templates
+            .iter()
+            .filter(|t| t.width <= width && t.height <= height)
+            .collect();
+        if fitting.is_empty() {
+            continue;
+        }
+        let vault = fitting[rng.range(0, fitting.len())];
+        let corner = room.interior().next().unwrap();
+        let x = corner.x + (width - vault.width) / 2;
+        let y = corner.y + (height - vault.height) / 2;
+        spawns.extend(vault.stamp(map, x, y));
+    }
+    spawns
+}
@@ -0,0 +1,98 @@
+use super::{
+    CombatStats, Experience, GameLog, Initiative, ParticleBuilder, Player, Position, Viewshed,
+};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Max HP gained at each level
+pub const HP_PER_LEVEL: i32 = 5;
+
+/// Revealing this many tiles gives one experience point
+pub const TILES_PER_XP: i32 = 20;
+
+const LEVEL_UP_PARTICLE_MS: f32 = 600.;
+
+/// The perks the player picks from when levelling up
+#[derive(PartialEq, Copy, Clone)]
+pub enum Perk {
+    KeenEyes,  // longer viewshed range
+    StrongArm, // more melee power
+    QuickFeet, // faster movement
+}
+
+impl Perk {
+    pub const ALL: [Perk; 3] = [Perk::KeenEyes, Perk::StrongArm, Perk::QuickFeet];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Perk::KeenEyes => "Keen eyes: see 2 tiles farther",
+            Perk::StrongArm => "Strong arm: +1 melee power",
+            Perk::QuickFeet => "Quick feet: act 20% more often",
+        }
+    }
+}
+
+/// Experience needed to go from `level` to the next one
+pub const fn xp_to_next_level(level: i32) -> i32 {
+    level * 50
+}
+
+/// Experience awarded for killing something with these stats
+pub const fn xp_for_kill(stats: &CombatStats) -> i32 {
+    stats.max_hp + stats.power * 2
+}
+
+/// Levels the player up if they have enough experience, returns `true` if they did
+pub fn check_level_up(ecs: &mut World) -> bool {
+    let players = ecs.read_storage::<Player>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut stats = ecs.write_storage::<CombatStats>();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    let mut levelled_up = false;
+    let positions = ecs.read_storage::<Position>();
+    let mut particles = ecs.write_resource::<ParticleBuilder>();
+    let entities = ecs.entities();
+    for (ent, _player, exp, stats) in (&entities, &players, &mut experience, &mut stats).join() {
+        let needed = xp_to_next_level(exp.level);
+        if exp.xp < needed {
+            continue;
+        }
+        exp.xp -= needed;
+        exp.level += 1;
+        stats.max_hp += HP_PER_LEVEL;
+        stats.hp = stats.max_hp;
+        log.log(format!("Welcome to level {}!", exp.level));
+        if let Some(pos) = positions.get(ent) {
+            particles.burst(
+                pos.x,
+                pos.y,
+                RGB::named(rltk::GOLD),
+                rltk::to_cp437('*'),
+                LEVEL_UP_PARTICLE_MS,
+            );
+        }
+        levelled_up = true;
+    }
+    levelled_up
+}
+
+pub fn apply_perk(ecs: &mut World, perk: Perk) {
+    let players = ecs.read_storage::<Player>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut stats = ecs.write_storage::<CombatStats>();
+    let mut initiatives = ecs.write_storage::<Initiative>();
+
+    for (_player, viewshed, stats, initiative) in
+        (&players, &mut viewsheds, &mut stats, &mut initiatives).join()
+    {
+        match perk {
+            Perk::KeenEyes => {
+                viewshed.range += 2;
+                viewshed.dirty = true;
+            }
+            Perk::StrongArm => stats.power += 1,
+            Perk::QuickFeet => initiative.speed += 2,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use super::{
+    CombatStats, DefenseBonus, Equipped, GameLog, Map, Name, ParticleBuilder, Position, Projectile,
+    Ranged, Renderable, SufferDamage, WantsToShoot, HIT_PARTICLE_MS, PARTICLE_RENDER_ORDER,
+};
+use rltk::{Point, RGB};
+use specs::prelude::*;
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Ranged>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, DefenseBonus>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Entity>, // the player
+        Read<'a, LazyUpdate>,   // to create the projectiles
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            mut wants_shoot,
+            positions,
+            names,
+            combat_stats,
+            ranged,
+            equipped,
+            defense_bonuses,
+            mut inflict_damage,
+            mut log,
+            player,
+            lazy,
+            mut particles,
+        ) = data;
+
+        for (shooter, wants, pos, name) in (&entities, &wants_shoot, &positions, &names).join() {
+            // their own ranged attack, or the weapon they wear
+            let weapon = ranged.get(shooter).or_else(|| {
+                (&ranged, &equipped)
+                    .join()
+                    .find(|(_r, eq)| eq.owner == shooter)
+                    .map(|(r, _eq)| r)
+            });
+            let weapon = match weapon {
+                Some(weapon) => weapon,
+                None => continue,
+            };
+
+            let from = Point::new(pos.x, pos.y);
+            let path = map.projectile_path(from, wants.target, weapon.range);
+
+            // the first fighter on the way gets hit, whoever it is. Not from the tile index,
+            // that is rebuilt only after everyone moved
+            let hit = path.last().and_then(|tile| {
+                (&entities, &positions, &combat_stats)
+                    .join()
+                    .find(|(_e, p, s)| p.x == tile.x && p.y == tile.y && s.hp > 0)
+                    .map(|(e, _p, _s)| e)
+            });
+            match hit {
+                None => log.log(format!("{} shoots, and misses", name.name)),
+                Some(target) => {
+                    let mut defense = combat_stats.get(target).unwrap().defense;
+                    for (bonus, eq) in (&defense_bonuses, &equipped).join() {
+                        if eq.owner == target {
+                            defense += bonus.defense;
+                        }
+                    }
+                    let damage = i32::max(0, weapon.power - defense);
+                    let target_name = names.get(target).map_or("something", |n| n.name.as_str());
+                    log.log(format!(
+                        "{} shoots {}, for {} hp",
+                        name.name, target_name, damage
+                    ));
+                    SufferDamage::new_damage(
+                        &mut inflict_damage,
+                        target,
+                        damage,
+                        shooter == *player,
+                    );
+                    let tile = path.last().unwrap();
+                    particles.request(
+                        tile.x,
+                        tile.y,
+                        RGB::named(rltk::ORANGE),
+                        rltk::to_cp437('‼'),
+                        HIT_PARTICLE_MS,
+                    );
+                }
+            }
+
+            // the flight, just for show
+            if let Some(first) = path.first() {
+                lazy.create_entity(&entities)
+                    .with(Position {
+                        x: first.x,
+                        y: first.y,
+                    })
+                    .with(Renderable {
+                        glyph: rltk::to_cp437('*'),
+                        fg: RGB::named(rltk::WHITE),
+                        bg: RGB::named(rltk::BLACK),
+                        render_order: PARTICLE_RENDER_ORDER,
+                        sprite: None,
+                    })
+                    .with(Projectile {
+                        path,
+                        step: 0,
+                        timer: 0.,
+                    })
+                    .build();
+            }
+        }
+
+        wants_shoot.clear();
+    }
+}
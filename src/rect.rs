@@ -1,3 +1,10 @@
+use rltk::{Point, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
+/// A room: the rows and columns at `x1` and `y1` are its walls, the floor goes from
+/// `x1 + 1` to `x2` and from `y1 + 1` to `y2`
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub struct Rect {
     pub x1: i32,
     pub x2: i32,
@@ -16,13 +23,207 @@ impl Rect {
         }
     }
 
+    pub const fn width(&self) -> i32 {
+        self.x2 - self.x1
+    }
+
+    pub const fn height(&self) -> i32 {
+        self.y2 - self.y1
+    }
+
+    /// How many floor tiles there are
+    pub const fn area(&self) -> i32 {
+        self.width() * self.height()
+    }
+
     /// Returns `true` if overlaps with another
     pub const fn intersect(&self, other: &Self) -> bool {
         self.x1 <= other.x2 && self.x2 >= other.x1 && self.y1 <= other.y2 && self.y2 >= other.y1
     }
 
+    /// Returns `true` if the point is on the floor of the rect
+    pub const fn contains(&self, point: Point) -> bool {
+        point.x > self.x1 && point.x <= self.x2 && point.y > self.y1 && point.y <= self.y2
+    }
+
     /// Returns the center of the Rect
     pub const fn center(&self) -> (i32, i32) {
         ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
     }
+
+    /// The floor tiles, row by row
+    pub fn interior(&self) -> impl Iterator<Item = Point> {
+        let (x1, x2) = (self.x1 + 1, self.x2);
+        (self.y1 + 1..=self.y2).flat_map(move |y| (x1..=x2).map(move |x| Point::new(x, y)))
+    }
+
+    /// The outermost floor tiles, going round clockwise from the top left corner
+    pub fn perimeter(&self) -> impl Iterator<Item = Point> {
+        let (left, right, top, bottom) = (self.x1 + 1, self.x2, self.y1 + 1, self.y2);
+        let top_row = (left..=right).map(move |x| Point::new(x, top));
+        let right_column = (top + 1..=bottom).map(move |y| Point::new(right, y));
+        let bottom_row = (left..right)
+            .rev()
+            .filter(move |_| bottom > top)
+            .map(move |x| Point::new(x, bottom));
+        let left_column = (top + 1..bottom)
+            .rev()
+            .filter(move |_| right > left)
+            .map(move |y| Point::new(left, y));
+        top_row
+            .chain(right_column)
+            .chain(bottom_row)
+            .chain(left_column)
+    }
+
+    /// Cuts the rect with a horizontal line, `offset` rows below the top.
+    /// The row of the cut is the wall of the bottom half, and no floor of either.
+    /// `None` if a half would be left without floor
+    pub fn split_horizontally(&self, offset: i32) -> Option<(Rect, Rect)> {
+        if offset < 2 || offset >= self.height() {
+            return None;
+        }
+        let y = self.y1 + offset;
+        Some((Rect { y2: y - 1, ..*self }, Rect { y1: y, ..*self }))
+    }
+
+    /// Cuts the rect with a vertical line, `offset` columns right of the left side.
+    /// The column of the cut is the wall of the right half, and no floor of either.
+    /// `None` if a half would be left without floor
+    pub fn split_vertically(&self, offset: i32) -> Option<(Rect, Rect)> {
+        if offset < 2 || offset >= self.width() {
+            return None;
+        }
+        let x = self.x1 + offset;
+        Some((Rect { x2: x - 1, ..*self }, Rect { x1: x, ..*self }))
+    }
+
+    /// The same rect, `margin` tiles smaller on every side
+    pub fn shrink(&self, margin: i32) -> Rect {
+        self.expand(-margin)
+    }
+
+    /// The same rect, `margin` tiles bigger on every side
+    pub fn expand(&self, margin: i32) -> Rect {
+        Rect {
+            x1: self.x1 - margin,
+            x2: self.x2 + margin,
+            y1: self.y1 - margin,
+            y2: self.y2 + margin,
+        }
+    }
+
+    /// The smallest rect containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            x1: min(self.x1, other.x1),
+            x2: max(self.x2, other.x2),
+            y1: min(self.y1, other.y1),
+            y2: max(self.y2, other.y2),
+        }
+    }
+
+    /// A random floor tile
+    pub fn random_point(&self, rng: &mut RandomNumberGenerator) -> Point {
+        Point::new(
+            rng.range(self.x1 + 1, self.x2 + 1),
+            rng.range(self.y1 + 1, self.y2 + 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(coords: &[(i32, i32)]) -> Vec<Point> {
+        coords.iter().map(|(x, y)| Point::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn interior_of_a_single_tile() {
+        let rect = Rect::new(4, 6, 1, 1);
+        assert_eq!(rect.interior().collect::<Vec<_>>(), points(&[(5, 7)]));
+        assert_eq!(rect.perimeter().collect::<Vec<_>>(), points(&[(5, 7)]));
+    }
+
+    #[test]
+    fn one_wide_and_one_high() {
+        let column = Rect::new(0, 0, 1, 3);
+        assert_eq!(
+            column.interior().collect::<Vec<_>>(),
+            points(&[(1, 1), (1, 2), (1, 3)])
+        );
+        assert_eq!(
+            column.perimeter().collect::<Vec<_>>(),
+            points(&[(1, 1), (1, 2), (1, 3)])
+        );
+
+        let row = Rect::new(0, 0, 3, 1);
+        assert_eq!(
+            row.interior().collect::<Vec<_>>(),
+            points(&[(1, 1), (2, 1), (3, 1)])
+        );
+        assert_eq!(
+            row.perimeter().collect::<Vec<_>>(),
+            points(&[(1, 1), (2, 1), (3, 1)])
+        );
+    }
+
+    #[test]
+    fn perimeter_goes_round_once() {
+        let rect = Rect::new(0, 0, 3, 3);
+        assert_eq!(
+            rect.perimeter().collect::<Vec<_>>(),
+            points(&[
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (3, 2),
+                (3, 3),
+                (2, 3),
+                (1, 3),
+                (1, 2)
+            ])
+        );
+        assert_eq!(rect.interior().count() as i32, rect.area());
+    }
+
+    #[test]
+    fn split_leaves_a_wall_between_the_halves() {
+        let rect = Rect::new(0, 0, 10, 8);
+
+        let (top, bottom) = rect.split_horizontally(4).unwrap();
+        assert_eq!(top, Rect { y2: 3, ..rect });
+        assert_eq!(bottom, Rect { y1: 4, ..rect });
+        assert!(!top.interior().any(|p| bottom.contains(p)));
+        assert_eq!(top.area() + bottom.area() + rect.width(), rect.area());
+
+        let (left, right) = rect.split_vertically(5).unwrap();
+        assert_eq!(left, Rect { x2: 4, ..rect });
+        assert_eq!(right, Rect { x1: 5, ..rect });
+        assert!(!left.interior().any(|p| right.contains(p)));
+        assert_eq!(left.area() + right.area() + rect.height(), rect.area());
+    }
+
+    #[test]
+    fn union_covers_both() {
+        let a = Rect::new(2, 3, 4, 2);
+        let b = Rect::new(5, 1, 6, 3);
+        let both = a.union(&b);
+        assert_eq!(both, Rect::new(2, 1, 9, 4));
+        assert!(a.interior().chain(b.interior()).all(|p| both.contains(p)));
+        assert_eq!(a.union(&a), a);
+    }
+
+    #[test]
+    fn split_needs_floor_on_both_sides() {
+        let rect = Rect::new(0, 0, 4, 4);
+        assert!(rect.split_horizontally(1).is_none());
+        assert!(rect.split_horizontally(4).is_none());
+        assert!(rect.split_vertically(0).is_none());
+        assert!(rect.split_vertically(4).is_none());
+        assert!(rect.split_horizontally(2).is_some());
+        assert!(rect.split_vertically(3).is_some());
+    }
 }
@@ -0,0 +1,22 @@
+use rltk::rex::XpFile;
+
+rltk::embedded_resource!(TITLE_SCREEN, "../resources/nyan.xp");
+rltk::embedded_resource!(VAULT_POOLS, "../resources/vault_pools.xp");
+
+/// The REX Paint images baked into the binary
+pub struct RexAssets {
+    pub title: XpFile,
+    pub vaults: Vec<XpFile>,
+}
+
+impl RexAssets {
+    pub fn load() -> RexAssets {
+        rltk::link_resource!(TITLE_SCREEN, "../resources/nyan.xp");
+        rltk::link_resource!(VAULT_POOLS, "../resources/vault_pools.xp");
+
+        RexAssets {
+            title: XpFile::from_resource("../resources/nyan.xp").unwrap(),
+            vaults: vec![XpFile::from_resource("../resources/vault_pools.xp").unwrap()],
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use super::{GameLog, Map};
+use rltk::Point;
+use specs::prelude::*;
+
+/// The room the player was in at the last tick
+#[derive(Default)]
+pub struct PlayerRoom {
+    pub room: Option<usize>,
+}
+
+/// Tells the player when they walk into a themed room
+pub struct RoomSystem {}
+
+impl<'a> System<'a> for RoomSystem {
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>, // the player position
+        WriteExpect<'a, PlayerRoom>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, mut player_room, mut log) = data;
+
+        let room = map.rooms.iter().position(|r| r.contains(*player_pos));
+        if room != player_room.room {
+            if let Some(name) = room.and_then(|i| map.room_theme(i).name()) {
+                log.log(format!("You enter {}", name));
+            }
+            player_room.room = room;
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use super::{Map, Rect, TileType};
+use rltk::{Point, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
+
+/// Percent of the tiles of a flooded room that are under water
+const FLOODED_PERCENT: i32 = 40;
+/// Percent of the ring around a monster den that is rubble
+const RUBBLE_PERCENT: i32 = 50;
+
+/// What a room looks like, and what is found in it
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum RoomTheme {
+    Plain,
+    Lake,       // a pool in the middle
+    Flooded,    // puddles everywhere
+    PillarHall, // rows of pillars
+    Treasure,   // more loot, and traps to guard it, behind a wall with one way in
+    MonsterDen, // more monsters, no loot, inside a ring of rubble
+}
+
+/// How many monsters, and the chance of each kind of item: one in `n`, 0 is never
+pub struct SpawnTable {
+    pub monsters: i32,
+    pub equipment: i32,
+    pub potions: i32,
+    pub rations: i32,
+    pub traps: i32,
+}
+
+impl RoomTheme {
+    /// How the room is called when the player walks in, `None` for the plain ones
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            RoomTheme::Plain => None,
+            RoomTheme::Lake => Some("the lake room"),
+            RoomTheme::Flooded => Some("the flooded hall"),
+            RoomTheme::PillarHall => Some("the pillar hall"),
+            RoomTheme::Treasure => Some("the treasure room"),
+            RoomTheme::MonsterDen => Some("the monster den"),
+        }
+    }
+
+    pub fn spawn_table(&self) -> SpawnTable {
+        match self {
+            RoomTheme::Plain | RoomTheme::PillarHall => SpawnTable {
+                monsters: 1,
+                equipment: 2,
+                potions: 3,
+                rations: 4,
+                traps: 3,
+            },
+            RoomTheme::Lake => SpawnTable {
+                monsters: 1,
+                equipment: 3,
+                potions: 3,
+                rations: 2,
+                traps: 0,
+            },
+            RoomTheme::Flooded => SpawnTable {
+                monsters: 1,
+                equipment: 3,
+                potions: 3,
+                rations: 4,
+                traps: 2,
+            },
+            RoomTheme::Treasure => SpawnTable {
+                monsters: 1,
+                equipment: 1,
+                potions: 1,
+                rations: 2,
+                traps: 1,
+            },
+            RoomTheme::MonsterDen => SpawnTable {
+                monsters: 3,
+                equipment: 0,
+                potions: 0,
+                rations: 2,
+                traps: 0,
+            },
+        }
+    }
+}
+
+/// Picks a theme for each room but the first one, and changes their tiles to match.
+/// The outer ring of floor is left alone, so the corridors stay connected
+pub fn theme_rooms(map: &mut Map, rng: &mut RandomNumberGenerator) {
+    map.room_themes = vec![RoomTheme::Plain; map.rooms.len()];
+    for i in 1..map.rooms.len() {
+        let theme = match rng.roll_dice(1, 10) {
+            1 => RoomTheme::Lake,
+            2 => RoomTheme::Flooded,
+            3 => RoomTheme::PillarHall,
+            4 => RoomTheme::Treasure,
+            5 => RoomTheme::MonsterDen,
+            _ => RoomTheme::Plain,
+        };
+        map.room_themes[i] = theme;
+
+        let room = map.rooms[i];
+        let inner = room.shrink(1);
+        match theme {
+            RoomTheme::Lake => {
+                let pool = room.shrink(2);
+                let (cx, cy) = pool.center();
+                let radius = (pool.width().min(pool.height()) as f32) / 2.;
+                for tile in pool.interior() {
+                    let distance =
+                        rltk::DistanceAlg::Pythagoras.distance2d(tile, Point::new(cx, cy));
+                    if distance <= radius {
+                        set_tile(map, tile, TileType::Water);
+                    }
+                }
+            }
+            RoomTheme::Flooded => {
+                for tile in inner.interior() {
+                    if rng.roll_dice(1, 100) <= FLOODED_PERCENT {
+                        set_tile(map, tile, TileType::Water);
+                    }
+                }
+                fill_pockets(map, &room, TileType::Water);
+            }
+            RoomTheme::PillarHall => {
+                for tile in inner.interior() {
+                    if (tile.x - room.x1) % 2 == 0 && (tile.y - room.y1) % 2 == 0 {
+                        set_tile(map, tile, TileType::Wall);
+                    }
+                }
+            }
+            RoomTheme::Treasure => {
+                // an alcove: a wall one tile in from the outer ring, with a gap to go in.
+                // Not in a corner, there the way in would be diagonal
+                let ring: Vec<Point> = inner.perimeter().collect();
+                let sides: Vec<Point> = ring
+                    .iter()
+                    .filter(|p| {
+                        (p.x != inner.x1 + 1 && p.x != inner.x2)
+                            || (p.y != inner.y1 + 1 && p.y != inner.y2)
+                    })
+                    .copied()
+                    .collect();
+                let gap = sides.get(rng.range(0, sides.len().max(1))).copied();
+                for tile in ring {
+                    if Some(tile) != gap {
+                        set_tile(map, tile, TileType::Wall);
+                    }
+                }
+                fill_pockets(map, &room, TileType::Wall);
+            }
+            RoomTheme::MonsterDen => {
+                for tile in inner.perimeter() {
+                    if rng.roll_dice(1, 100) <= RUBBLE_PERCENT {
+                        set_tile(map, tile, TileType::Wall);
+                    }
+                }
+                fill_pockets(map, &room, TileType::Wall);
+            }
+            RoomTheme::Plain => {}
+        }
+    }
+}
+
+/// Turns into `fill` the floor of the room that can't be reached from its outer ring,
+/// so nothing gets spawned where it can't be walked to
+fn fill_pockets(map: &mut Map, room: &Rect, fill: TileType) {
+    let walkable = |map: &Map, p: Point| !map.tiles[map.xy_idx(p.x, p.y)].is_solid();
+    let mut reached: Vec<Point> = room.perimeter().filter(|p| walkable(map, *p)).collect();
+    let mut open = reached.clone();
+    while let Some(p) = open.pop() {
+        for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let next = Point::new(p.x + dx, p.y + dy);
+            if room.contains(next) && walkable(map, next) && !reached.contains(&next) {
+                reached.push(next);
+                open.push(next);
+            }
+        }
+    }
+    for tile in room.interior() {
+        if walkable(map, tile) && !reached.contains(&tile) {
+            set_tile(map, tile, fill);
+        }
+    }
+}
+
+fn set_tile(map: &mut Map, tile: Point, tile_type: TileType) {
+    let idx = map.xy_idx(tile.x, tile.y);
+    map.tiles[idx] = tile_type;
+}
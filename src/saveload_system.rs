@@ -0,0 +1,290 @@
+use super::components::*;
+use super::{Map, Overworld};
+use rltk::Point;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, MarkerAllocator, SerializeComponents, SimpleMarker,
+    SimpleMarkerAllocator,
+};
+use std::convert::Infallible;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+const SAVEGAME: &str = "./savegame.json";
+
+// one call to the (de)serializer per component type
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $marker:ty, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<Infallible, $marker>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )?;
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<Infallible, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &$data.0, // entities
+            &mut $data.1, // marker
+            &mut $data.2, // allocater
+            &mut $de,
+        )?;
+        )*
+    };
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_game(_ecs: &mut World) -> Result<(), String> {
+    Ok(())
+}
+
+/// Writes the savegame, the error is for the log
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_game(ecs: &mut World) -> Result<(), String> {
+    let writer =
+        File::create(SAVEGAME).map_err(|e| format!("Unable to write {}: {}", SAVEGAME, e))?;
+    write_world(ecs, writer).map_err(|e| format!("Unable to save the game: {}", e))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_world<W: Write>(ecs: &mut World, writer: W) -> Result<(), serde_json::Error> {
+    // the map is a resource, so it travels inside a temporary entity
+    let mapcopy = ecs.get_mut::<Map>().unwrap().clone();
+    let overworld = (*ecs.fetch::<Overworld>()).clone();
+    let savehelper = ecs
+        .create_entity()
+        .with(SerializationHelper {
+            map: mapcopy,
+            overworld,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    let result = write_components::<SerializeMe, W>(ecs, writer);
+    ecs.delete_entity(savehelper).expect("Crash on cleanup");
+    result
+}
+
+/// Writes the components of the entities with a `SimpleMarker<T>`
+fn write_components<T: 'static + Send + Sync, W: Write>(
+    ecs: &World,
+    writer: W,
+) -> Result<(), serde_json::Error> {
+    {
+        let data = (ecs.entities(), ecs.read_storage::<SimpleMarker<T>>());
+
+        let mut serializer = serde_json::Serializer::new(writer);
+        serialize_individually!(
+            ecs,
+            serializer,
+            data,
+            SimpleMarker<T>,
+            Position,
+            Renderable,
+            Player,
+            Viewshed,
+            Monster,
+            BlocksTile,
+            Name,
+            Initiative,
+            MyTurn,
+            LastSeen,
+            LightSource,
+            Awareness,
+            Tracker,
+            Sneaking,
+            CombatStats,
+            Experience,
+            Item,
+            InBackpack,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenseBonus,
+            Consumable,
+            StatusEffects,
+            InflictsStatus,
+            Hidden,
+            EntryTrigger,
+            ProvidesFood,
+            HungerClock,
+            Ranged,
+            Swimmer,
+            Breath,
+            Boat,
+            SerializationHelper
+        );
+    }
+    Ok(())
+}
+
+/// Writes the entities of a level the player is leaving, and deletes them.
+/// They come back with `unstash_entities`
+pub fn stash_entities(ecs: &mut World, to_stash: &[Entity]) -> String {
+    // the markers of the stash are counted from 0 each time, so they can't meet
+    // the ones of the entities already there when they come back
+    ecs.insert(SimpleMarkerAllocator::<StashMe>::new());
+    {
+        let mut allocator = ecs.write_resource::<SimpleMarkerAllocator<StashMe>>();
+        let mut markers = ecs.write_storage::<SimpleMarker<StashMe>>();
+        for entity in to_stash {
+            allocator.mark(*entity, &mut markers);
+        }
+    }
+    let mut data = Vec::new();
+    write_components::<StashMe, _>(ecs, &mut data).expect("Unable to stash the level");
+    for entity in to_stash {
+        ecs.delete_entity(*entity).expect("Unable to delete entity");
+    }
+    String::from_utf8(data).expect("The stash is not valid utf-8")
+}
+
+/// Brings back the entities written by `stash_entities`, ready to be saved again
+pub fn unstash_entities(ecs: &mut World, data: &str) -> Result<(), serde_json::Error> {
+    ecs.insert(SimpleMarkerAllocator::<StashMe>::new());
+    let result = read_components::<StashMe>(ecs, data);
+    {
+        let entities = ecs.entities();
+        let mut stashed = ecs.write_storage::<SimpleMarker<StashMe>>();
+        let mut allocator = ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>();
+        let mut markers = ecs.write_storage::<SimpleMarker<SerializeMe>>();
+        for (entity, _stashed) in (&entities, &stashed).join() {
+            allocator.mark(entity, &mut markers);
+        }
+        stashed.clear();
+    }
+    result
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVEGAME).exists()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_game(_ecs: &mut World) -> Result<(), String> {
+    Ok(())
+}
+
+/// Replaces the game with the savegame. If the savegame is broken the game goes on as it was,
+/// and the error is for the log
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_game(ecs: &mut World) -> Result<(), String> {
+    let data =
+        fs::read_to_string(SAVEGAME).map_err(|e| format!("Unable to read {}: {}", SAVEGAME, e))?;
+
+    // the current game is kept aside, to go back to it if the savegame can't be read
+    let mut backup = Vec::new();
+    write_world(ecs, &mut backup).map_err(|e| format!("Unable to load the game: {}", e))?;
+
+    clear_world(ecs);
+    if let Err(e) = read_world(ecs, &data) {
+        clear_world(ecs);
+        let backup = String::from_utf8(backup).expect("The game is not valid utf-8");
+        read_world(ecs, &backup).expect("Unable to restore the game");
+        return Err(format!("The savegame is broken: {}", e));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn clear_world(ecs: &mut World) {
+    let mut to_delete = Vec::new();
+    for e in ecs.entities().join() {
+        to_delete.push(e);
+    }
+    for del in to_delete.iter() {
+        ecs.delete_entity(*del).expect("Deletion failed");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_world(ecs: &mut World, data: &str) -> Result<(), serde_json::Error> {
+    read_components::<SerializeMe>(ecs, data)?;
+
+    // put back the resources: the map, the player entity and their position
+    let mut deleteme: Option<Entity> = None;
+    {
+        let entities = ecs.entities();
+        let helper = ecs.read_storage::<SerializationHelper>();
+        let player = ecs.read_storage::<Player>();
+        let position = ecs.read_storage::<Position>();
+        for (e, h) in (&entities, &helper).join() {
+            let mut worldmap = ecs.write_resource::<Map>();
+            *worldmap = h.map.clone();
+            worldmap.tile_content = vec![Vec::new(); worldmap.tiles.len()];
+            *ecs.write_resource::<Overworld>() = h.overworld.clone();
+            deleteme = Some(e);
+        }
+        for (e, _p, pos) in (&entities, &player, &position).join() {
+            let mut ppos = ecs.write_resource::<Point>();
+            *ppos = Point::new(pos.x, pos.y);
+            let mut player_resource = ecs.write_resource::<Entity>();
+            *player_resource = e;
+        }
+    }
+    let helper = deleteme.ok_or_else(|| serde::de::Error::custom("the map is missing"))?;
+    ecs.delete_entity(helper).expect("Unable to delete helper");
+    Ok(())
+}
+
+/// Creates the entities written by `write_components`, marked with a `SimpleMarker<T>`
+fn read_components<T: 'static + Send + Sync>(
+    ecs: &mut World,
+    data: &str,
+) -> Result<(), serde_json::Error> {
+    let mut de = serde_json::Deserializer::from_str(data);
+    let mut d = (
+        &mut ecs.entities(),
+        &mut ecs.write_storage::<SimpleMarker<T>>(),
+        &mut ecs.write_resource::<SimpleMarkerAllocator<T>>(),
+    );
+    deserialize_individually!(
+        ecs,
+        de,
+        d,
+        Position,
+        Renderable,
+        Player,
+        Viewshed,
+        Monster,
+        BlocksTile,
+        Name,
+        Initiative,
+        MyTurn,
+        LastSeen,
+        LightSource,
+        Awareness,
+        Tracker,
+        Sneaking,
+        CombatStats,
+        Experience,
+        Item,
+        InBackpack,
+        Equippable,
+        Equipped,
+        MeleePowerBonus,
+        DefenseBonus,
+        Consumable,
+        StatusEffects,
+        InflictsStatus,
+        Hidden,
+        EntryTrigger,
+        ProvidesFood,
+        HungerClock,
+        Ranged,
+        Swimmer,
+        Breath,
+        Boat,
+        SerializationHelper
+    );
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::{Map, MyTurn};
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+
+/// Scent left on the tile where the player stands, it fades a bit every player turn
+pub const FRESH_SCENT: i32 = 100;
+const SCENT_DECAY: i32 = 2;
+
+/// Whether the scent layer is drawn over the map, to see what the trackers smell
+#[derive(Default)]
+pub struct ScentOverlay {
+    pub visible: bool,
+}
+
+/// The player leaves a trail behind, and the old one fades away
+pub struct ScentSystem {}
+
+impl<'a> System<'a> for ScentSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, Point>,  // the player position
+        ReadExpect<'a, Entity>, // the player
+        ReadStorage<'a, MyTurn>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, player_pos, player, turns) = data;
+
+        // once per player turn
+        if !turns.contains(*player) {
+            return;
+        }
+        if map.scent.len() != map.tiles.len() {
+            map.scent = vec![0; map.tiles.len()]; // a map from an older savegame
+        }
+        for scent in map.scent.iter_mut() {
+            *scent = (*scent - SCENT_DECAY).max(0);
+        }
+        let idx = map.xy_idx(player_pos.x, player_pos.y);
+        map.scent[idx] = FRESH_SCENT;
+    }
+}
+
+/// Tints every tile with some scent on it, the fresher the redder
+pub fn draw_scent(ecs: &World, ctx: &mut Rltk) {
+    if !ecs.fetch::<ScentOverlay>().visible {
+        return;
+    }
+    let map = ecs.fetch::<Map>();
+    for (idx, scent) in map.scent.iter().enumerate() {
+        if *scent <= 0 {
+            continue;
+        }
+        let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+        let strength = *scent as f32 / FRESH_SCENT as f32;
+        ctx.set_bg(x, y, RGB::from_f32(strength, 0., 0.2));
+    }
+}
@@ -0,0 +1,493 @@
+use super::{
+    Awareness, AwarenessState, BlocksTile, Boat, Breath, CombatStats, Consumable, DefenseBonus,
+    EntryTrigger, EquipmentSlot, Equippable, Experience, Hidden, HungerClock, HungerState,
+    InflictsStatus, Initiative, Item, LightSource, MeleePowerBonus, Monster, Name, Player,
+    Position, ProvidesFood, Ranged, Renderable, SerializeMe, StatusEffect, StatusKind, Swimmer,
+    Tracker, TrapKind, Viewshed, ALARM_TRAP_SPRITE, ARMOUR_SPRITE, BOAT_SPRITE, BOW_SPRITE,
+    DAGGER_SPRITE, FLOOD_TRAP_SPRITE, HELMET_SPRITE, MURKY_POTION_SPRITE, PLAYER_SPRITE,
+    RATION_SPRITE, REGENERATION_POTION_SPRITE, SHIELD_SPRITE, SPIKE_TRAP_SPRITE, SPUTACCHIO_SPRITE,
+    TELEPORT_TRAP_SPRITE, TORCH_SPRITE, VOKASTATI_SPRITE, VOSKLAMATI_SPRITE, WELL_FED_TURNS,
+};
+use rltk::{to_cp437, RandomNumberGenerator, RGB};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+/// Turns the player can swim before running out of breath, in the shallows
+const PLAYER_BREATH: i32 = 12;
+/// One monster in this many follows the scent of the player, whatever its kind
+const TRACKER_CHANCE: i32 = 4;
+
+/// Creates the player entity and returns it
+pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position {
+            x: player_x,
+            y: player_y,
+        })
+        .with(Name {
+            name: "Player".to_string(),
+        })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::YELLOW),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 0,
+            sprite: Some(PLAYER_SPRITE),
+        })
+        .with(BlocksTile {})
+        .with(Player {})
+        .with(Initiative {
+            speed: 10,
+            energy: 0,
+        })
+        .with(LightSource {
+            range: 6,
+            colour: RGB::from_f32(1.0, 0.9, 0.6),
+        })
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 10,
+            dirty: true,
+        })
+        .with(CombatStats {
+            max_hp: 30,
+            hp: 30,
+            defense: 2,
+            power: 5,
+        })
+        .with(Experience {
+            level: 1,
+            xp: 0,
+            explored: 0,
+        })
+        .with(HungerClock {
+            state: HungerState::WellFed,
+            duration: WELL_FED_TURNS,
+        })
+        .with(Swimmer {})
+        .with(Breath {
+            breath: PLAYER_BREATH,
+            max_breath: PLAYER_BREATH,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MonsterKind {
+    Vosklamati,
+    Sputacchio,
+    Vokastati,
+}
+
+/// Creates a random monster, `i` makes its name unique
+pub fn monster(ecs: &mut World, x: i32, y: i32, i: usize, rng: &mut RandomNumberGenerator) {
+    let kind = match rng.roll_dice(1, 3) {
+        1 => MonsterKind::Vosklamati,
+        2 => MonsterKind::Sputacchio,
+        _ => MonsterKind::Vokastati,
+    };
+    monster_of_kind(ecs, x, y, i, kind, is_tracker(rng));
+}
+
+/// Rolls if a new monster is a tracker
+fn is_tracker(rng: &mut RandomNumberGenerator) -> bool {
+    rng.roll_dice(1, TRACKER_CHANCE) == 1
+}
+
+/// Creates a monster of the kind, `tracker` if it follows the scent of the player
+/// (the Vokastati always do)
+pub fn monster_of_kind(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    i: usize,
+    kind: MonsterKind,
+    tracker: bool,
+) {
+    let glyph: rltk::FontCharType;
+    let sprite: rltk::FontCharType;
+    let name: String;
+    let speed: i32;
+    let light: Option<LightSource>;
+    let stats: CombatStats;
+    let inflicts: InflictsStatus;
+    let mut ranged: Option<Ranged> = None;
+    let mut tracker = tracker;
+    let mut swimmer = false;
+
+    match kind {
+        // fast, acts twice per player turn, and glows in the dark
+        MonsterKind::Vosklamati => {
+            glyph = to_cp437('$');
+            sprite = VOSKLAMATI_SPRITE;
+            name = "Vosklamati".to_string();
+            speed = 20;
+            light = Some(LightSource {
+                range: 3,
+                colour: RGB::from_f32(0.4, 1.0, 0.4),
+            });
+            stats = CombatStats {
+                max_hp: 8,
+                hp: 8,
+                defense: 0,
+                power: 3,
+            };
+            inflicts = InflictsStatus {
+                effect: StatusEffect {
+                    kind: StatusKind::Poison,
+                    turns: 4,
+                    magnitude: 1,
+                },
+                chance: 30,
+            };
+        }
+        // spits from afar and keeps away from the player, even through the water
+        MonsterKind::Sputacchio => {
+            swimmer = true;
+            glyph = to_cp437('&');
+            sprite = SPUTACCHIO_SPRITE;
+            name = "Sputacchio".to_string();
+            speed = 10;
+            light = None;
+            stats = CombatStats {
+                max_hp: 6,
+                hp: 6,
+                defense: 0,
+                power: 1,
+            };
+            inflicts = InflictsStatus {
+                effect: StatusEffect {
+                    kind: StatusKind::Poison,
+                    turns: 2,
+                    magnitude: 1,
+                },
+                chance: 10,
+            };
+            ranged = Some(Ranged { range: 6, power: 3 });
+        }
+        // slow, acts every other turn, but sniffs out the player
+        MonsterKind::Vokastati => {
+            tracker = true;
+            glyph = to_cp437('£');
+            sprite = VOKASTATI_SPRITE;
+            name = "Vokastati".to_string();
+            speed = 5;
+            light = None;
+            stats = CombatStats {
+                max_hp: 16,
+                hp: 16,
+                defense: 1,
+                power: 5,
+            };
+            inflicts = InflictsStatus {
+                effect: StatusEffect {
+                    kind: StatusKind::Paralysis,
+                    turns: 2,
+                    magnitude: 0,
+                },
+                chance: 15,
+            };
+        }
+    }
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: format!("{} #{}", &name, i),
+        })
+        .with(Monster {})
+        .with(BlocksTile {})
+        .with(Initiative { speed, energy: 0 })
+        .maybe_with(light)
+        .with(Awareness {
+            state: AwarenessState::Unaware,
+            investigate: None,
+        })
+        .with(Renderable {
+            glyph,
+            fg: RGB::named(rltk::GREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 1,
+            sprite: Some(sprite),
+        })
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 5,
+            dirty: true,
+        })
+        .with(stats)
+        .with(inflicts)
+        .maybe_with(ranged)
+        .maybe_with(tracker.then_some(Tracker {}))
+        .maybe_with(swimmer.then_some(Swimmer {}))
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// A torch on the wall, lighting up the room
+pub fn torch(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: "Torch".to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437('*'),
+            fg: RGB::named(rltk::ORANGE),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(TORCH_SPRITE),
+        })
+        .with(LightSource {
+            range: 8,
+            colour: RGB::from_f32(1.0, 0.6, 0.2),
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// Something to wear, picked at random
+pub fn random_equipment(ecs: &mut World, x: i32, y: i32, rng: &mut RandomNumberGenerator) {
+    match rng.roll_dice(1, 5) {
+        1 => equipment(
+            ecs,
+            x,
+            y,
+            "Dagger",
+            '/',
+            DAGGER_SPRITE,
+            EquipmentSlot::Melee,
+            2,
+            0,
+        ),
+        2 => equipment(
+            ecs,
+            x,
+            y,
+            "Shield",
+            '(',
+            SHIELD_SPRITE,
+            EquipmentSlot::Shield,
+            0,
+            1,
+        ),
+        3 => equipment(
+            ecs,
+            x,
+            y,
+            "Helmet",
+            '^',
+            HELMET_SPRITE,
+            EquipmentSlot::Head,
+            0,
+            1,
+        ),
+        4 => bow(ecs, x, y),
+        _ => equipment(
+            ecs,
+            x,
+            y,
+            "Leather armour",
+            '[',
+            ARMOUR_SPRITE,
+            EquipmentSlot::Body,
+            0,
+            2,
+        ),
+    }
+}
+
+/// Shoots across the room, once worn
+pub fn bow(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: "Bow".to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437(')'),
+            fg: RGB::named(rltk::CYAN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(BOW_SPRITE),
+        })
+        .with(Item {})
+        .with(Equippable {
+            slot: EquipmentSlot::Ranged,
+        })
+        .with(Ranged { range: 8, power: 4 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn equipment(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    name: &str,
+    glyph: char,
+    sprite: rltk::FontCharType,
+    slot: EquipmentSlot,
+    power: i32,
+    defense: i32,
+) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437(glyph),
+            fg: RGB::named(rltk::CYAN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(sprite),
+        })
+        .with(Item {})
+        .with(Equippable { slot })
+        .maybe_with((power != 0).then_some(MeleePowerBonus { power }))
+        .maybe_with((defense != 0).then_some(DefenseBonus { defense }))
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// A potion with a status effect, picked at random
+pub fn random_potion(ecs: &mut World, x: i32, y: i32, rng: &mut RandomNumberGenerator) {
+    let (name, colour, sprite, kind, turns, magnitude) = match rng.roll_dice(1, 2) {
+        1 => (
+            "Regeneration potion",
+            rltk::MAGENTA,
+            REGENERATION_POTION_SPRITE,
+            StatusKind::Regeneration,
+            10,
+            1,
+        ),
+        _ => (
+            "Murky potion",
+            rltk::OLIVE,
+            MURKY_POTION_SPRITE,
+            StatusKind::Confusion,
+            5,
+            0,
+        ),
+    };
+
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437('!'),
+            fg: RGB::named(colour),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(sprite),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(InflictsStatus {
+            effect: StatusEffect {
+                kind,
+                turns,
+                magnitude,
+            },
+            chance: 100,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// A hidden trap, picked at random
+pub fn random_trap(ecs: &mut World, x: i32, y: i32, rng: &mut RandomNumberGenerator) {
+    let (name, trap, sprite) = match rng.roll_dice(1, 4) {
+        1 => ("Spike trap", TrapKind::Spikes, SPIKE_TRAP_SPRITE),
+        2 => ("Teleport trap", TrapKind::Teleport, TELEPORT_TRAP_SPRITE),
+        3 => ("Alarm trap", TrapKind::Alarm, ALARM_TRAP_SPRITE),
+        _ => ("Flood trap", TrapKind::Flood, FLOOD_TRAP_SPRITE),
+    };
+
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: name.to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437('^'),
+            fg: RGB::named(rltk::RED),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(sprite),
+        })
+        .with(Hidden {})
+        .with(EntryTrigger { trap })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+pub fn ration(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: "Ration".to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437('%'),
+            fg: RGB::named(rltk::SANDY_BROWN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(RATION_SPRITE),
+        })
+        .with(Item {})
+        .with(Consumable {})
+        .with(ProvidesFood {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// Carried in the backpack, it takes the player anywhere on the water
+pub fn boat(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Name {
+            name: "Boat".to_string(),
+        })
+        .with(Renderable {
+            glyph: to_cp437('▬'),
+            fg: RGB::named(rltk::SADDLEBROWN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+            sprite: Some(BOAT_SPRITE),
+        })
+        .with(Item {})
+        .with(Boat {})
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+}
+
+/// Spawns what a marker in a prefab stands for, returns `false` if the marker is unknown
+pub fn spawn_marker(
+    ecs: &mut World,
+    x: i32,
+    y: i32,
+    marker: char,
+    i: usize,
+    rng: &mut RandomNumberGenerator,
+) -> bool {
+    match marker {
+        '$' => monster_of_kind(ecs, x, y, i, MonsterKind::Vosklamati, is_tracker(rng)),
+        '&' => monster_of_kind(ecs, x, y, i, MonsterKind::Sputacchio, is_tracker(rng)),
+        '£' => monster_of_kind(ecs, x, y, i, MonsterKind::Vokastati, is_tracker(rng)),
+        'M' | 'g' => monster(ecs, x, y, i, rng),
+        '!' => random_potion(ecs, x, y, rng),
+        '%' => ration(ecs, x, y),
+        '^' => random_trap(ecs, x, y, rng),
+        '[' => random_equipment(ecs, x, y, rng),
+        ')' => bow(ecs, x, y),
+        '*' => torch(ecs, x, y),
+        'B' => boat(ecs, x, y),
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,76 @@
+use super::{
+    CombatStats, GameLog, Initiative, MyTurn, RunState, StatusEffects, StatusKind, SufferDamage,
+    TURN_COST,
+};
+use specs::prelude::*;
+
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, MyTurn>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RunState>,
+        ReadExpect<'a, Entity>, // the player
+    );
+
+    // effects tick at the start of each turn of the entity
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut status,
+            mut turns,
+            mut initiatives,
+            mut stats,
+            mut damage,
+            mut log,
+            mut runstate,
+            player,
+        ) = data;
+
+        let mut paralysed: Vec<Entity> = Vec::new();
+        for (ent, active, _turn, stats) in (&entities, &mut status, &turns, &mut stats).join() {
+            for effect in active.effects.iter_mut() {
+                match effect.kind {
+                    StatusKind::Poison => {
+                        SufferDamage::new_damage(&mut damage, ent, effect.magnitude, false);
+                        if ent == *player {
+                            log.log(format!("The poison burns, {} hp", effect.magnitude));
+                        }
+                    }
+                    StatusKind::Regeneration => {
+                        stats.hp = i32::min(stats.max_hp, stats.hp + effect.magnitude);
+                    }
+                    StatusKind::Paralysis => paralysed.push(ent),
+                    StatusKind::Confusion => {} // handled when moving
+                }
+                effect.turns -= 1;
+                if effect.turns <= 0 && ent == *player {
+                    log.log(format!(
+                        "You are not {} anymore",
+                        effect.kind.name().to_lowercase()
+                    ));
+                }
+            }
+            active.effects.retain(|e| e.turns > 0);
+        }
+
+        // the paralysed lose their turn
+        for ent in paralysed {
+            turns.remove(ent);
+            if let Some(initiative) = initiatives.get_mut(ent) {
+                initiative.energy -= TURN_COST;
+            }
+            if ent == *player {
+                log.log("You can't move!");
+                *runstate = RunState::Running;
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Boat, Breath, GameLog, InBackpack, Map, MyTurn, Position, SufferDamage, TileType};
+use specs::prelude::*;
+
+// breath lost each turn in the water, and got back on dry land
+const SHALLOWS_BREATH: i32 = 1;
+const DEEP_WATER_BREATH: i32 = 2;
+const BREATH_RECOVERY: i32 = 2;
+
+// damage taken each turn once out of breath
+const EXHAUSTION_DAMAGE: i32 = 1;
+const DROWNING_DAMAGE: i32 = 3;
+
+pub struct SwimmingSystem {}
+
+impl<'a> System<'a> for SwimmingSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Breath>,
+        ReadStorage<'a, MyTurn>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Boat>,
+        ReadExpect<'a, Entity>, // the player
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, GameLog>,
+    );
+
+    // the breath goes up and down once per turn of whoever has it
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            positions,
+            mut breaths,
+            turns,
+            backpacks,
+            boats,
+            player,
+            mut damage,
+            mut log,
+        ) = data;
+
+        for (ent, pos, breath, _turn) in (&entities, &positions, &mut breaths, &turns).join() {
+            let tile = map.tiles[map.xy_idx(pos.x, pos.y)];
+            let afloat = (&backpacks, &boats)
+                .join()
+                .any(|(pack, _)| pack.owner == ent);
+            if !tile.is_swimmable() || afloat {
+                breath.breath = (breath.breath + BREATH_RECOVERY).min(breath.max_breath);
+                continue;
+            }
+
+            let deep = tile == TileType::DeepWater;
+            if breath.breath > 0 {
+                let before = breath.breath;
+                breath.breath -= if deep {
+                    DEEP_WATER_BREATH
+                } else {
+                    SHALLOWS_BREATH
+                };
+                breath.breath = breath.breath.max(0);
+                let warning = breath.max_breath / 3;
+                if ent == *player && before > warning && breath.breath <= warning {
+                    log.log("You are running out of breath");
+                }
+                continue;
+            }
+
+            if ent == *player {
+                log.log(if deep {
+                    "You are drowning!"
+                } else {
+                    "You are too tired to keep swimming!"
+                });
+            }
+            let amount = if deep {
+                DROWNING_DAMAGE
+            } else {
+                EXHAUSTION_DAMAGE
+            };
+            SufferDamage::new_damage(&mut damage, ent, amount, false);
+        }
+    }
+}
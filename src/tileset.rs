@@ -0,0 +1,72 @@
+use super::TileType;
+use rltk::FontCharType;
+use serde::{Deserialize, Serialize};
+
+/// The consoles, from the bottom: the sprites below, the glyphs and the GUI on top
+pub const TILES_CONSOLE: usize = 0;
+pub const ASCII_CONSOLE: usize = 1;
+
+/// The sprite sheet, 16x16 sprites on a 256x256 image
+pub const TILESET_FONT: &str = "tiles.png";
+pub const SPRITE_SIZE: u32 = 16;
+
+// where things are on the sprite sheet
+const WALL_SPRITE: FontCharType = 0;
+const FLOOR_SPRITE: FontCharType = 1;
+pub const PLAYER_SPRITE: FontCharType = 2;
+const WATER_SPRITE: FontCharType = 3;
+const DEEP_WATER_SPRITE: FontCharType = 4;
+const SHALLOWS_SPRITE: FontCharType = 5;
+const SAND_SPRITE: FontCharType = 6;
+const FORD_SPRITE: FontCharType = 7;
+const BRIDGE_SPRITE: FontCharType = 8;
+const DOWN_STAIRS_SPRITE: FontCharType = 9;
+const UP_STAIRS_SPRITE: FontCharType = 10;
+pub const VOSKLAMATI_SPRITE: FontCharType = 11;
+pub const SPUTACCHIO_SPRITE: FontCharType = 12;
+pub const VOKASTATI_SPRITE: FontCharType = 13;
+pub const TORCH_SPRITE: FontCharType = 14;
+pub const DAGGER_SPRITE: FontCharType = 15;
+pub const SHIELD_SPRITE: FontCharType = 16;
+pub const HELMET_SPRITE: FontCharType = 17;
+pub const ARMOUR_SPRITE: FontCharType = 18;
+pub const BOW_SPRITE: FontCharType = 19;
+pub const REGENERATION_POTION_SPRITE: FontCharType = 20;
+pub const MURKY_POTION_SPRITE: FontCharType = 21;
+pub const RATION_SPRITE: FontCharType = 22;
+pub const BOAT_SPRITE: FontCharType = 23;
+pub const SPIKE_TRAP_SPRITE: FontCharType = 24;
+pub const TELEPORT_TRAP_SPRITE: FontCharType = 25;
+pub const ALARM_TRAP_SPRITE: FontCharType = 26;
+pub const FLOOD_TRAP_SPRITE: FontCharType = 27;
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum RenderMode {
+    Ascii,
+    Tiles,
+}
+
+impl RenderMode {
+    pub fn next(&self) -> RenderMode {
+        match self {
+            RenderMode::Ascii => RenderMode::Tiles,
+            RenderMode::Tiles => RenderMode::Ascii,
+        }
+    }
+}
+
+/// The sprite for a tile. The entities have theirs in the `Renderable`
+pub fn tile_sprite(tile: TileType) -> FontCharType {
+    match tile {
+        TileType::Wall => WALL_SPRITE,
+        TileType::Ground => FLOOR_SPRITE,
+        TileType::Water => WATER_SPRITE,
+        TileType::DeepWater => DEEP_WATER_SPRITE,
+        TileType::Shallows => SHALLOWS_SPRITE,
+        TileType::Sand => SAND_SPRITE,
+        TileType::Ford => FORD_SPRITE,
+        TileType::Bridge => BRIDGE_SPRITE,
+        TileType::DownStairs => DOWN_STAIRS_SPRITE,
+        TileType::UpStairs => UP_STAIRS_SPRITE,
+    }
+}
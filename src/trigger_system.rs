@@ -0,0 +1,156 @@
+use super::{
+    EntityMoved, EntryTrigger, GameLog, Hidden, Map, Name, NoiseEvents, ParticleBuilder, Position,
+    SufferDamage, TileType, TrapKind, Viewshed,
+};
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Damage dealt by the spikes
+const SPIKE_DAMAGE: i32 = 4;
+/// Monsters this far away hear the alarm
+const ALARM_RADIUS: f32 = 25.;
+/// Radius of the area that gets flooded
+const FLOOD_RADIUS: f32 = 3.;
+
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteExpect<'a, Map>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Viewshed>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, NoiseEvents>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, Point>, // the player position, if they get teleported
+        ReadExpect<'a, Entity>, // the player
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut map,
+            mut moved,
+            mut positions,
+            triggers,
+            mut hidden,
+            names,
+            mut damage,
+            mut viewsheds,
+            mut log,
+            mut noises,
+            mut rng,
+            mut player_pos,
+            player,
+            mut particles,
+        ) = data;
+
+        // who stepped on what, using the tile index of the map
+        let mut triggered: Vec<(Entity, Entity)> = Vec::new();
+        for (ent, _moved, pos) in (&entities, &moved, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            for content in map.tile_content[idx].iter() {
+                if *content != ent && triggers.get(*content).is_some() {
+                    triggered.push((ent, *content));
+                }
+            }
+        }
+        moved.clear();
+
+        for (victim, trap_entity) in triggered {
+            let trap = triggers.get(trap_entity).unwrap().trap;
+            let (trap_x, trap_y) = match positions.get(trap_entity) {
+                Some(pos) => (pos.x, pos.y),
+                None => continue,
+            };
+            let victim_name = names
+                .get(victim)
+                .map_or("Something".to_string(), |n| n.name.clone());
+
+            // once triggered, everybody knows it's there
+            hidden.remove(trap_entity);
+            log.log(format!(
+                "{} triggers a {} trap!",
+                victim_name,
+                trap_name(trap)
+            ));
+
+            match trap {
+                TrapKind::Spikes => {
+                    SufferDamage::new_damage(&mut damage, victim, SPIKE_DAMAGE, false);
+                }
+                TrapKind::Teleport => {
+                    if map.rooms.is_empty() {
+                        continue;
+                    }
+                    let room = &map.rooms[rng.range(0, map.rooms.len())];
+                    let Some(Point { x, y }) = map.room_spot(room) else {
+                        continue;
+                    };
+                    if let Some(pos) = positions.get_mut(victim) {
+                        particles.teleport(pos.x, pos.y);
+                        particles.teleport(x, y);
+                        pos.x = x;
+                        pos.y = y;
+                    }
+                    if let Some(viewshed) = viewsheds.get_mut(victim) {
+                        viewshed.dirty = true;
+                    }
+                    if victim == *player {
+                        player_pos.x = x;
+                        player_pos.y = y;
+                    }
+                }
+                TrapKind::Alarm => {
+                    log.log("A loud bell rings through the caves");
+                    noises.make(trap_x, trap_y, ALARM_RADIUS);
+                }
+                TrapKind::Flood => {
+                    log.log("Water gushes out of the ground!");
+                    flood(&mut map, trap_x, trap_y);
+                }
+            }
+
+            if trap.single_use() {
+                entities.delete(trap_entity).expect("Unable to delete trap");
+            }
+        }
+    }
+}
+
+fn trap_name(trap: TrapKind) -> &'static str {
+    match trap {
+        TrapKind::Spikes => "spike",
+        TrapKind::Teleport => "teleport",
+        TrapKind::Alarm => "alarm",
+        TrapKind::Flood => "flood",
+    }
+}
+
+/// Turns the ground around into water, leaving alone the tiles where something is standing
+fn flood(map: &mut Map, x: i32, y: i32) {
+    let centre = Point::new(x, y);
+    let radius = FLOOD_RADIUS as i32;
+    for ty in y - radius..=y + radius {
+        for tx in x - radius..=x + radius {
+            if tx < 1 || tx >= map.width - 1 || ty < 1 || ty >= map.height - 1 {
+                continue;
+            }
+            if rltk::DistanceAlg::Pythagoras.distance2d(centre, Point::new(tx, ty)) > FLOOD_RADIUS {
+                continue;
+            }
+            let idx = map.xy_idx(tx, ty);
+            if map.tile_content[idx].is_empty() && map.tiles[idx] == TileType::Ground {
+                map.tiles[idx] = TileType::Water;
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use super::{Map, TileType};
+use crate::corridors::l_path;
+use rltk::rex::XpFile;
+use rltk::{Point, RandomNumberGenerator};
+
+/// Attempts at finding an empty spot for the vault
+const PLACEMENT_TRIES: i32 = 200;
+const CORRIDOR_WIDTH: i32 = 2;
+
+/// A hand-made piece of map, from a REX Paint file or a text template
+pub struct Vault {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<Option<TileType>>, // `None` leaves the map as it is
+    pub spawns: Vec<(i32, i32, char)>,
+}
+
+impl Vault {
+    /// The first layer is the terrain, the second one marks what spawns where
+    pub fn from_xp(xp: &XpFile) -> Vault {
+        let terrain = &xp.layers[0];
+        let mut vault = Vault {
+            width: terrain.width as i32,
+            height: terrain.height as i32,
+            tiles: Vec::new(),
+            spawns: Vec::new(),
+        };
+        for y in 0..terrain.height {
+            for x in 0..terrain.width {
+                let cell = terrain.get(x, y).unwrap();
+                let tile = if cell.bg.is_transparent() {
+                    None
+                } else {
+                    tile_for(glyph_char(cell.ch))
+                };
+                vault.tiles.push(tile);
+            }
+        }
+
+        if let Some(markers) = xp.layers.get(1) {
+            for y in 0..markers.height {
+                for x in 0..markers.width {
+                    let cell = markers.get(x, y).unwrap();
+                    if cell.ch != 0 && cell.ch != 32 && !cell.bg.is_transparent() {
+                        vault.spawns.push((x as i32, y as i32, glyph_char(cell.ch)));
+                    }
+                }
+            }
+        }
+        vault
+    }
+
+    /// One line of text per row. The terrain glyphs are `.` ground, `~` water and `#` wall,
+    /// a space leaves the map as it is, anything else spawns something on the ground
+    pub fn from_text(template: &str) -> Vault {
+        // the templates start on the line after the opening quote
+        let rows: Vec<&str> = template.trim_start_matches('\n').lines().collect();
+        let mut vault = Vault {
+            width: rows.iter().map(|r| r.chars().count()).max().unwrap_or(0) as i32,
+            height: rows.len() as i32,
+            tiles: Vec::new(),
+            spawns: Vec::new(),
+        };
+        for (y, row) in rows.iter().enumerate() {
+            let mut glyphs = row.chars();
+            for x in 0..vault.width {
+                let glyph = glyphs.next().unwrap_or(' ');
+                let tile = match glyph {
+                    ' ' => None,
+                    _ => match tile_for(glyph) {
+                        Some(tile) => Some(tile),
+                        None => {
+                            vault.spawns.push((x, y as i32, glyph));
+                            Some(TileType::Ground)
+                        }
+                    },
+                };
+                vault.tiles.push(tile);
+            }
+        }
+        vault
+    }
+
+    /// Copies the vault on the map with the top left corner at `x`, `y`.
+    /// Returns the spawn markers in map coordinates
+    pub fn stamp(&self, map: &mut Map, x: i32, y: i32) -> Vec<(i32, i32, char)> {
+        for vy in 0..self.height {
+            for vx in 0..self.width {
+                if let Some(tile) = self.tiles[(vy * self.width + vx) as usize] {
+                    let idx = map.xy_idx(x + vx, y + vy);
+                    map.tiles[idx] = tile;
+                }
+            }
+        }
+        self.spawns
+            .iter()
+            .map(|(vx, vy, marker)| (x + vx, y + vy, *marker))
+            .collect()
+    }
+
+    /// Digs a corridor from the door closest to the nearest room, the vault being at `x`, `y`
+    pub fn connect(&self, map: &mut Map, x: i32, y: i32) {
+        let centre = Point::new(x + self.width / 2, y + self.height / 2);
+        let distance = |a: Point, b: Point| rltk::DistanceAlg::Pythagoras.distance2d(a, b);
+        let room = match map
+            .rooms
+            .iter()
+            .map(|room| Point::from(room.center()))
+            .min_by(|a, b| distance(centre, *a).total_cmp(&distance(centre, *b)))
+        {
+            Some(room) => room,
+            None => return,
+        };
+        let door = self
+            .doors()
+            .into_iter()
+            .map(|(vx, vy)| Point::new(x + vx, y + vy))
+            .min_by(|a, b| distance(room, *a).total_cmp(&distance(room, *b)))
+            .unwrap_or(centre);
+        // out of the door first, then towards the room
+        let vertical_door = door.y == y || door.y == y + self.height - 1;
+        map.dig_path(&l_path(door, room, !vertical_door), CORRIDOR_WIDTH);
+    }
+
+    /// The ground tiles on the border, where corridors can come in
+    fn doors(&self) -> Vec<(i32, i32)> {
+        let mut doors = Vec::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let border = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+                if border && self.tiles[(y * self.width + x) as usize] == Some(TileType::Ground) {
+                    doors.push((x, y));
+                }
+            }
+        }
+        doors
+    }
+}
+
+fn glyph_char(ch: u32) -> char {
+    rltk::to_char(ch as u8)
+}
+
+/// The terrain for a glyph
+fn tile_for(glyph: char) -> Option<TileType> {
+    match glyph {
+        '.' => Some(TileType::Ground),
+        '~' => Some(TileType::Water),
+        '#' => Some(TileType::Wall),
+        _ => None,
+    }
+}
+
+/// Stamps one of the vaults where the map is still untouched, and digs a corridor
+/// to the closest room. Returns the spawn markers in map coordinates, none if it didn't fit
+pub fn stamp_random_vault(
+    map: &mut Map,
+    rng: &mut RandomNumberGenerator,
+    vaults: &[XpFile],
+) -> Vec<(i32, i32, char)> {
+    if vaults.is_empty() || map.rooms.is_empty() {
+        return Vec::new();
+    }
+    let vault = Vault::from_xp(&vaults[rng.range(0, vaults.len())]);
+    if vault.width + 2 >= map.width || vault.height + 2 >= map.height {
+        return Vec::new();
+    }
+
+    for _ in 0..PLACEMENT_TRIES {
+        let x = rng.range(1, map.width - vault.width - 1);
+        let y = rng.range(1, map.height - vault.height - 1);
+        if !is_untouched(map, x - 1, y - 1, vault.width + 2, vault.height + 2) {
+            continue;
+        }
+        let spawns = vault.stamp(map, x, y);
+        vault.connect(map, x, y);
+        return spawns;
+    }
+    Vec::new()
+}
+
+/// `true` if no room nor corridor was dug in the area yet
+fn is_untouched(map: &Map, x: i32, y: i32, width: i32, height: i32) -> bool {
+    (y..y + height)
+        .all(|ty| (x..x + width).all(|tx| map.tiles[map.xy_idx(tx, ty)] == TileType::Wall))
+}
@@ -1,22 +1,47 @@
-use crate::Player;
+use crate::{Experience, GameLog, Hidden, LastSeen, Monster, Player, TILES_PER_XP};
 
 use super::{Map, Position, Viewshed};
-use rltk::{field_of_view, Point};
+use rltk::{field_of_view, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// Without light, the player only sees this close
+const DARKVISION_RANGE: f32 = 2.5;
+
+/// A d20 roll at least this high spots a hidden trap
+const SPOT_DIFFICULTY: i32 = 12;
+
 pub struct VisibilitySystem {}
 
 impl<'a> System<'a> for VisibilitySystem {
+    #[allow(clippy::type_complexity)]
     type SystemData = (
         WriteExpect<'a, Map>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
+        ReadStorage<'a, Monster>,
+        WriteStorage<'a, LastSeen>,
+        WriteStorage<'a, Experience>,
+        WriteStorage<'a, Hidden>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, GameLog>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            monsters,
+            mut last_seen,
+            mut experience,
+            mut hidden,
+            mut rng,
+            mut log,
+        ) = data;
 
         // all the entities that have both position and viewshed
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
@@ -33,16 +58,61 @@ impl<'a> System<'a> for VisibilitySystem {
                 // if this is the player, reveal what they can see
                 let p: Option<&Player> = player.get(ent);
                 if let Some(_p) = p {
+                    let centre = Point::new(pos.x, pos.y);
+                    viewshed.visible_tiles.retain(|t| {
+                        rltk::DistanceAlg::Pythagoras.distance2d(centre, *t) <= DARKVISION_RANGE
+                            || map.is_lit(map.xy_idx(t.x, t.y))
+                    });
+
+                    let previously_visible = map.visible_tiles.clone();
                     for t in map.visible_tiles.iter_mut() {
                         *t = false
                     }
+                    let mut newly_revealed = 0;
                     for vis in viewshed.visible_tiles.iter() {
                         let idx = map.xy_idx(vis.x, vis.y);
+                        if !map.revealed_tiles[idx] {
+                            newly_revealed += 1;
+                        }
                         map.revealed_tiles[idx] = true;
                         map.visible_tiles[idx] = true;
+
+                        // a chance to spot what is hidden, when it comes into view
+                        if previously_visible[idx] {
+                            continue;
+                        }
+                        for content in map.tile_content[idx].iter() {
+                            if hidden.get(*content).is_some()
+                                && rng.roll_dice(1, 20) >= SPOT_DIFFICULTY
+                            {
+                                hidden.remove(*content);
+                                log.log("You spot a trap");
+                            }
+                        }
+                    }
+
+                    // exploring is rewarded too
+                    if let Some(exp) = experience.get_mut(ent) {
+                        exp.explored += newly_revealed;
+                        exp.xp += exp.explored / TILES_PER_XP;
+                        exp.explored %= TILES_PER_XP;
                     }
                 }
             }
         }
+
+        // remember where monsters were spotted, and forget the ghosts that the player
+        // can see are not there anymore
+        for (ent, pos, _monster) in (&entities, &pos, &monsters).join() {
+            if map.visible_tiles[map.xy_idx(pos.x, pos.y)] {
+                last_seen
+                    .insert(ent, LastSeen { x: pos.x, y: pos.y })
+                    .expect("Unable to insert last seen position");
+            } else if let Some(seen) = last_seen.get(ent) {
+                if map.visible_tiles[map.xy_idx(seen.x, seen.y)] {
+                    last_seen.remove(ent);
+                }
+            }
+        }
     }
 }